@@ -1,7 +1,6 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{ self, Mint, Token, TokenAccount, Transfer };
-
-use std::ops::Deref;
+use anchor_lang::solana_program::{ bpf_loader_upgradeable, program::invoke, system_instruction };
+use anchor_spl::token::{ self, CloseAccount, Mint, Token, TokenAccount, Transfer };
 
 declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
 
@@ -11,570 +10,3546 @@ const USDC_DECIMAL: u8 = 6;
 const WEN_DECIMAL:u8 = 9;
 // NOTE: we need to consider decimals when we calculate all amount
 // because decimals are different
-// So we use DIVIDER for 10^6 / 10 ^9
-const DIVIDER: u64 = 1000;
+// So token price is always USDC base units for one whole WEN token (10^9 units)
+const WEN_UNIT: u64 = 1_000_000_000;
+
+// Lock rate is in basis points
+const BPS_DENOMINATOR: u64 = 10_000;
+
+// Max lock duration: 4 years
+const MAX_LOCK_DURATION: i64 = 4 * 365 * 86400; // seconds
+
+// Max number of sale rounds per presale
+const MAX_ROUNDS: u8 = 8;
+// Value of `active_round` while no round is active
+const NO_ACTIVE_ROUND: u8 = u8::MAX;
+
+// Max number of lock tranches per user.
+// Purchases with the same lock schedule share a tranche, so this limits
+// locked purchases at different times unless the lock starts at TGE.
+const MAX_TRANCHES: usize = 16;
+
+// Max number of multisig admins, approvals are kept as a u16 bitmask
+const MAX_ADMINS: usize = 10;
+
+// Max delay of scheduled withdrawals: 30 days
+const MAX_WITHDRAWAL_DELAY: i64 = 30 * 86400; // seconds
+const MAX_WITHDRAW_DESTINATIONS: usize = 8;
+
+// Max length of presale name in bytes
+const MAX_NAME_LEN: usize = 32;
 
-// Locked Rate
-const LOCK_RATE: u64 = 50; // 50%
-const DENOMINATOR: u64 = 100;
+// Max lengths of metadata strings in bytes
+const MAX_DESCRIPTION_LEN: usize = 512;
+const MAX_URI_LEN: usize = 200;
+const MAX_SYMBOL_LEN: usize = 10;
 
-// Lock duration: 30 days
-const LOCK_DURATION: i64 = 30 * 86400; // seconds
+// Max platform fee on withdrawn USDC: 10%
+const MAX_FEE_BPS: u64 = 1_000;
+
+// Max number of payment mints allowed by the launchpad
+const MAX_PAYMENT_MINTS: usize = 4;
 
 #[program]
 pub mod presale {
     use super::*;
 
+    // Set up the launchpad config once per deployment.
+    // Only the program's upgrade authority can do it and becomes the config admin.
+    pub fn init_global_config(
+        ctx: Context<InitGlobalConfig>,
+        bump: u8,
+        params: GlobalConfigParams
+    ) -> ProgramResult {
+        let (program_data, _) = Pubkey::find_program_address(&[id().as_ref()], &bpf_loader_upgradeable::id());
+        if ctx.accounts.program_data.key() != program_data
+            || ctx.accounts.program_data.upgrade_authority_address != Some(ctx.accounts.admin.key()) {
+            return Err(ErrorCode::NotRight.into())
+        }
+
+        let global_config = &mut ctx.accounts.global_config;
+        global_config.admin = ctx.accounts.admin.key();
+        global_config.bump = bump;
+        global_config.apply(params)?;
+
+        emit_config_updated(global_config)
+    }
+
+    // Launchpad admin changes fees and payment mints, existing presales keep their fee
+    pub fn update_global_config(
+        ctx: Context<UpdateGlobalConfig>,
+        params: GlobalConfigParams
+    ) -> ProgramResult {
+        let global_config = &mut ctx.accounts.global_config;
+
+        if global_config.admin != ctx.accounts.admin.key() {
+            return Err(ErrorCode::NotRight.into())
+        }
+
+        global_config.apply(params)?;
+
+        emit_config_updated(global_config)
+    }
+
+    // Launchpad admin proposes a new admin, who has to accept it with `accept_config_admin`
+    pub fn propose_config_admin(
+        ctx: Context<UpdateGlobalConfig>,
+        new_admin: Pubkey
+    ) -> ProgramResult {
+        let global_config = &mut ctx.accounts.global_config;
+
+        if global_config.admin != ctx.accounts.admin.key() {
+            return Err(ErrorCode::NotRight.into())
+        }
+
+        if new_admin == Pubkey::default() || new_admin == global_config.admin {
+            return Err(ErrorCode::InvalidOwner.into())
+        }
+
+        global_config.pending_admin = new_admin;
+
+        emit!(ConfigAdminProposed {
+            global_config: global_config.key(),
+            admin: global_config.admin,
+            pending_admin: new_admin,
+            timestamp: Clock::get()?.unix_timestamp // Returns real-world time in second uint
+        });
+
+        Ok(())
+    }
+
+    // Proposed admin takes over the launchpad config
+    pub fn accept_config_admin(
+        ctx: Context<UpdateGlobalConfig>
+    ) -> ProgramResult {
+        let global_config = &mut ctx.accounts.global_config;
+
+        if global_config.pending_admin == Pubkey::default() {
+            return Err(ErrorCode::NoPendingOwner.into())
+        }
+
+        if global_config.pending_admin != ctx.accounts.admin.key() {
+            return Err(ErrorCode::NotRight.into())
+        }
+
+        let previous_admin = global_config.admin;
+        global_config.admin = global_config.pending_admin;
+        global_config.pending_admin = Pubkey::default();
+
+        emit!(ConfigAdminChanged {
+            global_config: global_config.key(),
+            previous_admin,
+            admin: global_config.admin,
+            timestamp: Clock::get()?.unix_timestamp // Returns real-world time in second uint
+        });
+
+        Ok(())
+    }
+
+    // Launchpad admin withdraws the admin proposal
+    pub fn cancel_config_admin_proposal(
+        ctx: Context<UpdateGlobalConfig>
+    ) -> ProgramResult {
+        let global_config = &mut ctx.accounts.global_config;
+
+        if global_config.admin != ctx.accounts.admin.key() {
+            return Err(ErrorCode::NotRight.into())
+        }
+
+        if global_config.pending_admin == Pubkey::default() {
+            return Err(ErrorCode::NoPendingOwner.into())
+        }
+
+        global_config.pending_admin = Pubkey::default();
+
+        emit!(ConfigAdminProposed {
+            global_config: global_config.key(),
+            admin: global_config.admin,
+            pending_admin: Pubkey::default(),
+            timestamp: Clock::get()?.unix_timestamp // Returns real-world time in second uint
+        });
+
+        Ok(())
+    }
+
     // Initialize all infos of tokens and pool
     pub fn initialize(
         ctx: Context<Initialize>,
-        presale_title: String,
+        presale_id: u64,
+        name: String,
         bumps: PoolBumps,
+        params: PresaleParams,
     ) -> ProgramResult {
         msg!("INITIALIZE POOL");
-        
+
+        if params.token_price < 1 {
+            return Err(ErrorCode::InvalidPrice.into())
+        }
+
+        if params.start_ts >= params.end_ts {
+            return Err(ErrorCode::InvalidSchedule.into())
+        }
+
+        if params.hard_cap < 1 || params.soft_cap > params.hard_cap {
+            return Err(ErrorCode::InvalidCap.into())
+        }
+
+        if params.max_contribution < 1 || params.min_contribution > params.max_contribution {
+            return Err(ErrorCode::InvalidContributionLimits.into())
+        }
+
+        if params.vesting_period < 0 {
+            return Err(ErrorCode::InvalidVesting.into())
+        }
+
+        validate_lock_terms(params.lock_rate, params.lock_duration)?;
+
+        if !(0..=MAX_WITHDRAWAL_DELAY).contains(&params.withdrawal_delay) {
+            return Err(ErrorCode::InvalidWithdrawalDelay.into())
+        }
+
+        if !ctx.accounts.global_config.is_payment_mint(&ctx.accounts.usdc_mint.key()) {
+            return Err(ErrorCode::PaymentMintNotAllowed.into())
+        }
+
+        // TGE is optional at init, but can't be before the sale ends
+        if params.tge_ts != 0 && params.tge_ts < params.end_ts {
+            return Err(ErrorCode::InvalidTge.into())
+        }
+
         let presale_account = &mut ctx.accounts.presale_account;
 
-        let name_bytes = presale_title.as_bytes();
-        let mut name_data = [b' '; 10];
+        let name_bytes = name.as_bytes();
+        if name_bytes.is_empty() || name_bytes.len() > MAX_NAME_LEN {
+            return Err(ErrorCode::InvalidName.into())
+        }
+        let mut name_data = [0u8; MAX_NAME_LEN];
         name_data[..name_bytes.len()].copy_from_slice(name_bytes);
 
-        presale_account.presale_title = name_data;
+        presale_account.creator = ctx.accounts.presale_owner.key();
+        presale_account.presale_id = presale_id;
+        presale_account.name = name_data;
         presale_account.bumps = bumps;
 
         presale_account.presale_owner = ctx.accounts.presale_owner.key();
+        presale_account.treasurer = ctx.accounts.presale_owner.key();
+        presale_account.operator = ctx.accounts.presale_owner.key();
+        presale_account.allocation_manager = ctx.accounts.presale_owner.key();
         presale_account.usdc_mint = ctx.accounts.usdc_mint.key();
         presale_account.wen_mint = ctx.accounts.wen_mint.key();
         presale_account.pool_usdc = ctx.accounts.pool_usdc.key();
         presale_account.pool_wen = ctx.accounts.pool_wen.key();
+        presale_account.token_price = params.token_price;
+        presale_account.start_ts = params.start_ts;
+        presale_account.end_ts = params.end_ts;
+        presale_account.soft_cap = params.soft_cap;
+        presale_account.hard_cap = params.hard_cap;
+        presale_account.min_contribution = params.min_contribution;
+        presale_account.max_contribution = params.max_contribution;
+        presale_account.active_round = NO_ACTIVE_ROUND;
+        presale_account.vesting_period = params.vesting_period;
+        presale_account.lock_rate = params.lock_rate;
+        presale_account.lock_duration = params.lock_duration;
+        presale_account.tge_ts = params.tge_ts;
+        presale_account.lock_from_tge = params.lock_from_tge;
+        presale_account.delivery_mode = params.delivery_mode;
+        presale_account.withdrawal_delay = params.withdrawal_delay;
+        presale_account.fee_bps = ctx.accounts.global_config.default_fee_bps;
+
+        // Creation fee in lamports goes to the launchpad wallet
+        let global_config = &mut ctx.accounts.global_config;
+        if global_config.creation_fee > 0 {
+            invoke(
+                &system_instruction::transfer(
+                    &ctx.accounts.presale_owner.key(),
+                    &ctx.accounts.fee_wallet.key(),
+                    global_config.creation_fee
+                ),
+                &[
+                    ctx.accounts.presale_owner.to_account_info(),
+                    ctx.accounts.fee_wallet.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ]
+            )?;
+        }
+
+        // Register the presale under the next sequential id
+        presale_account.registry_id = global_config.presale_count;
+
+        let registry_entry = &mut ctx.accounts.registry_entry;
+        registry_entry.id = global_config.presale_count;
+        registry_entry.presale = presale_account.key();
+        registry_entry.creator = presale_account.creator;
+        registry_entry.created_ts = Clock::get()?.unix_timestamp; // Returns real-world time in second uint
+        registry_entry.bump = presale_account.bumps.registry_entry;
+
+        global_config.presale_count = global_config.presale_count
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(PresaleInitialized {
+            presale: presale_account.key(),
+            creator: presale_account.creator,
+            presale_id,
+            registry_id: presale_account.registry_id,
+            usdc_mint: presale_account.usdc_mint,
+            wen_mint: presale_account.wen_mint,
+            start_ts: presale_account.start_ts,
+            end_ts: presale_account.end_ts,
+            timestamp: registry_entry.created_ts
+        });
 
         Ok(())
     }
 
-    // Init user account
-    pub fn init_user_account(
-        ctx: Context<InitUserAccount>, 
-        bump: u8, 
-        seed0: String, 
-        seed1: String
-    ) -> ProgramResult {
-        // Make as 1 string for pubkey
-        let mut owned_string: String = seed0.to_owned();
-        let another_owned_string: String = seed1.to_owned();
-        owned_string.push_str(&another_owned_string);
-        
-        msg!("INIT USER INFO ACCOUNT {:?}, {:?}", owned_string, ctx.accounts.user_authority.key().to_string());
-        if owned_string != ctx.accounts.user_authority.key().to_string() {
-            return Err(ErrorCode::FailedInit.into())
+    // Owner creates the display metadata of the presale
+    pub fn create_metadata(
+        ctx: Context<CreateMetadata>,
+        bump: u8,
+        params: MetadataParams
+    ) -> ProgramResult {
+        if ctx.accounts.presale_account.presale_owner != ctx.accounts.user_authority.key() {
+            return Err(ErrorCode::NotRight.into())
         }
 
-        let user_account = &mut ctx.accounts.user_account;
-        user_account.owner = ctx.accounts.user_authority.key();
-        user_account.bump = bump;
+        params.validate()?;
+
+        let metadata = &mut ctx.accounts.metadata;
+        metadata.presale = ctx.accounts.presale_account.key();
+        metadata.bump = bump;
+        metadata.apply(params);
+
+        emit!(MetadataUpdated {
+            presale: metadata.presale,
+            metadata: metadata.key(),
+            created: true,
+            timestamp: Clock::get()?.unix_timestamp // Returns real-world time in second uint
+        });
+
         Ok(())
     }
 
-    // stake SEEDED token into player
-    pub fn purchase(
-        ctx: Context<DepositUsdcForWenToken>,
-        amount: u64
+    // Owner replaces the display metadata of the presale
+    pub fn update_metadata(
+        ctx: Context<UpdateMetadata>,
+        params: MetadataParams
     ) -> ProgramResult {
-        msg!("Enter staking!!!");
-        if amount < 1 {
-            return Err(ErrorCode::InvalidAmount.into())
+        if ctx.accounts.presale_account.presale_owner != ctx.accounts.user_authority.key() {
+            return Err(ErrorCode::NotRight.into())
         }
 
-        if ctx.accounts.user_usdc.amount < amount {
-            return Err(ErrorCode::LowUSDC.into())
-        }
+        params.validate()?;
 
-        // Transfer user's USDC to pool USDC account.
-        {
-            let cpi_accounts = Transfer {
-                // storer address (user address)
-                from: ctx.accounts.user_usdc.to_account_info(),
-                to: ctx.accounts.pool_usdc.to_account_info(),
-                authority: ctx.accounts.user_authority.to_account_info(),
-            };
+        ctx.accounts.metadata.apply(params);
 
-            let cpi_program = ctx.accounts.token_program.to_account_info();
-            let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
-            token::transfer(cpi_ctx, amount)?;
-        }
+        emit!(MetadataUpdated {
+            presale: ctx.accounts.presale_account.key(),
+            metadata: ctx.accounts.metadata.key(),
+            created: false,
+            timestamp: Clock::get()?.unix_timestamp // Returns real-world time in second uint
+        });
 
-        // USDC decimal is 6 and WEN decimal is 9
-        let wen_amount = amount * DIVIDER;
-        let lock_amount = wen_amount * LOCK_RATE / DENOMINATOR;
-        let spend_amount = wen_amount - lock_amount;
+        Ok(())
+    }
 
-        // Transfer WEN token from pool token account to user's token account.
-        {
-            // Transfer seeded from pool account to the user's account.
-            let presale_title = ctx.accounts.presale_account.presale_title.as_ref();
-            let seeds = &[
-                presale_title.trim_ascii_whitespace(),
-                &[ctx.accounts.presale_account.bumps.presale_account],
-            ];
-            let signer = &[&seeds[..]];
+    // Operator can change the price of WEN token before the sale opens
+    pub fn set_token_price(
+        ctx: Context<UpdatePresale>,
+        token_price: u64
+    ) -> ProgramResult {
+        let presale_account = &mut ctx.accounts.presale_account;
 
-            let cpi_accounts = Transfer {
-                from: ctx.accounts.pool_wen.to_account_info(),
-                to: ctx.accounts.user_wen.to_account_info(),
-                authority: ctx.accounts.presale_account.to_account_info(),
-            };
-            let cpi_program = ctx.accounts.token_program.to_account_info();
-            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        if presale_account.operator != ctx.accounts.user_authority.key() {
+            return Err(ErrorCode::NotRight.into())
+        }
 
-            token::transfer(cpi_ctx, spend_amount)?;
+        if presale_account.approval_threshold > 0 {
+            return Err(ErrorCode::MultisigRequired.into())
         }
 
         let clock = Clock::get()?; // Returns real-world time in second uint
-        let user_account = &mut ctx.accounts.user_account;
-        let deposit_amount = user_account.deposit_amount;
-        let locked_amount = user_account.locked_amount;
+        presale_account.apply_token_price(token_price, clock.unix_timestamp)?;
 
-        // Lock some tokens and will be able to claim after `LOCK_DURATION` days.
-        user_account.locked_amount = locked_amount + lock_amount;
-        user_account.deposit_amount = deposit_amount + amount;
-        user_account.last_deposit_ts = clock.unix_timestamp;
+        emit!(TokenPriceChanged {
+            presale: presale_account.key(),
+            token_price,
+            timestamp: clock.unix_timestamp
+        });
 
         Ok(())
     }
 
-    pub fn claim_locked_wen(
-        ctx: Context<ClaimLockedWenToken>
-    ) -> ProgramResult {        
-        let user_account = &mut ctx.accounts.user_account;
-        let locked_amount = user_account.locked_amount;
-        let last_ts = user_account.last_deposit_ts;
+    // Operator can change per-wallet contribution limits before the sale opens
+    pub fn set_contribution_limits(
+        ctx: Context<UpdatePresale>,
+        min_contribution: u64,
+        max_contribution: u64
+    ) -> ProgramResult {
+        let presale_account = &mut ctx.accounts.presale_account;
 
-        if locked_amount < 1 {
-            return Err(ErrorCode::NotEnoughClaim.into())
+        if presale_account.operator != ctx.accounts.user_authority.key() {
+            return Err(ErrorCode::NotRight.into())
+        }
+
+        if presale_account.approval_threshold > 0 {
+            return Err(ErrorCode::MultisigRequired.into())
         }
 
         let clock = Clock::get()?; // Returns real-world time in second uint
-        let dur = last_ts - clock.unix_timestamp;
-        if dur < LOCK_DURATION {
-            return Err(ErrorCode::NotAbleClaim.into())
+        presale_account.apply_contribution_limits(min_contribution, max_contribution, clock.unix_timestamp)?;
+
+        emit!(ContributionLimitsChanged {
+            presale: presale_account.key(),
+            min_contribution,
+            max_contribution,
+            timestamp: clock.unix_timestamp
+        });
+
+        Ok(())
+    }
+
+    // Operator can set or move the TGE until it passes, while multisig is off
+    pub fn set_tge(
+        ctx: Context<UpdatePresale>,
+        tge_ts: i64
+    ) -> ProgramResult {
+        let presale_account = &mut ctx.accounts.presale_account;
+
+        if presale_account.operator != ctx.accounts.user_authority.key() {
+            return Err(ErrorCode::NotRight.into())
         }
 
-        let spend_amount = locked_amount;
-        if ctx.accounts.pool_wen.amount < spend_amount {
-            return Err(ErrorCode::LowPoolWen.into())
+        // Moving TGE delays claims, so it needs a proposal under multisig
+        if presale_account.approval_threshold > 0 {
+            return Err(ErrorCode::MultisigRequired.into())
         }
 
-        // Transfer WEN token from pool token account to user's token account.
-        {
-            // Transfer seeded from pool account to the user's account.
-            let presale_title = ctx.accounts.presale_account.presale_title.as_ref();
-            let seeds = &[
-                presale_title.trim_ascii_whitespace(),
-                &[ctx.accounts.presale_account.bumps.presale_account],
-            ];
-            let signer = &[&seeds[..]];
+        let clock = Clock::get()?; // Returns real-world time in second uint
+        presale_account.apply_tge(tge_ts, clock.unix_timestamp)?;
 
-            let cpi_accounts = Transfer {
-                from: ctx.accounts.pool_wen.to_account_info(),
-                to: ctx.accounts.user_wen.to_account_info(),
-                authority: ctx.accounts.presale_account.to_account_info(),
-            };
-            let cpi_program = ctx.accounts.token_program.to_account_info();
-            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        emit!(TgeChanged {
+            presale: presale_account.key(),
+            tge_ts,
+            timestamp: clock.unix_timestamp
+        });
+
+        Ok(())
+    }
+
+    // Anyone closes a sale that ended above its soft cap, raised USDC can be withdrawn after that.
+    // Buyers don't depend on the owner to start claiming.
+    pub fn finalize_presale(
+        ctx: Context<UpdatePresale>
+    ) -> ProgramResult {
+        let presale_account = &mut ctx.accounts.presale_account;
+
+        let clock = Clock::get()?; // Returns real-world time in second uint
+        presale_account.require_status(clock.unix_timestamp, &[PresaleStatus::Ended])?;
 
-            token::transfer(cpi_ctx, spend_amount)?;
+        if presale_account.total_raised < presale_account.soft_cap {
+            return Err(ErrorCode::SoftCapNotReached.into())
         }
 
-        // update info
-        user_account.locked_amount = 0;
-        user_account.last_deposit_ts = clock.unix_timestamp;
+        presale_account.status = PresaleStatus::Finalized;
 
-        Ok(())
+        emit!(PresaleFinalized {
+            presale: presale_account.key(),
+            total_raised: presale_account.total_raised,
+            timestamp: clock.unix_timestamp
+        });
 
+        Ok(())
     }
 
-    // After presale, admin can withdraw the remaining tokens
-    pub fn withdraw_usdc(
-        ctx: Context<WithdrawUSDC>
-    ) -> ProgramResult {        
+    // Owner aborts the sale, buyers can refund their deposit after that
+    pub fn cancel_presale(
+        ctx: Context<UpdatePresale>
+    ) -> ProgramResult {
         let presale_account = &mut ctx.accounts.presale_account;
-        
 
         if presale_account.presale_owner != ctx.accounts.user_authority.key() {
             return Err(ErrorCode::NotRight.into())
         }
 
-        let spend_amount = ctx.accounts.pool_usdc.amount;
-        if spend_amount < 1 {
-            return Err(ErrorCode::LowPoolUSDC.into())
+        let clock = Clock::get()?; // Returns real-world time in second uint
+        presale_account.require_status(clock.unix_timestamp, &[
+            PresaleStatus::Pending,
+            PresaleStatus::Active,
+            PresaleStatus::Ended
+        ])?;
+
+        presale_account.status = PresaleStatus::Cancelled;
+
+        emit!(PresaleCancelled {
+            presale: presale_account.key(),
+            total_raised: presale_account.total_raised,
+            timestamp: clock.unix_timestamp
+        });
+
+        Ok(())
+    }
+
+    // Operator stops purchases and/or claims in an emergency
+    pub fn pause(
+        ctx: Context<UpdatePresale>,
+        purchases: bool,
+        claims: bool
+    ) -> ProgramResult {
+        let presale_account = &mut ctx.accounts.presale_account;
+
+        if presale_account.operator != ctx.accounts.user_authority.key() {
+            return Err(ErrorCode::NotRight.into())
         }
 
-        // Transfer WEN token from pool token account to user's token account.
-        {
-            // Transfer seeded from pool account to the user's account.
-            let presale_title = ctx.accounts.presale_account.presale_title.as_ref();
-            let seeds = &[
-                presale_title.trim_ascii_whitespace(),
-                &[ctx.accounts.presale_account.bumps.presale_account],
-            ];
-            let signer = &[&seeds[..]];
+        if purchases {
+            presale_account.purchases_paused = true;
+        }
+        if claims {
+            presale_account.claims_paused = true;
+        }
 
-            let cpi_accounts = Transfer {
-                from: ctx.accounts.pool_usdc.to_account_info(),
-                to: ctx.accounts.user_usdc.to_account_info(),
-                authority: ctx.accounts.presale_account.to_account_info(),
-            };
-            let cpi_program = ctx.accounts.token_program.to_account_info();
-            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        emit!(PauseChanged {
+            presale: presale_account.key(),
+            purchases_paused: presale_account.purchases_paused,
+            claims_paused: presale_account.claims_paused,
+            timestamp: Clock::get()?.unix_timestamp // Returns real-world time in second uint
+        });
+
+        Ok(())
+    }
+
+    // Operator resumes purchases and/or claims
+    pub fn unpause(
+        ctx: Context<UpdatePresale>,
+        purchases: bool,
+        claims: bool
+    ) -> ProgramResult {
+        let presale_account = &mut ctx.accounts.presale_account;
+
+        if presale_account.operator != ctx.accounts.user_authority.key() {
+            return Err(ErrorCode::NotRight.into())
+        }
 
-            token::transfer(cpi_ctx, spend_amount)?;
+        if purchases {
+            presale_account.purchases_paused = false;
+        }
+        if claims {
+            presale_account.claims_paused = false;
         }
+
+        emit!(PauseChanged {
+            presale: presale_account.key(),
+            purchases_paused: presale_account.purchases_paused,
+            claims_paused: presale_account.claims_paused,
+            timestamp: Clock::get()?.unix_timestamp // Returns real-world time in second uint
+        });
+
         Ok(())
     }
 
-    // After presale, admin can withdraw the remaining tokens
-    pub fn withdraw_wen(
-        ctx: Context<WithdrawWenToken>
-    ) -> ProgramResult {        
+    // Owner proposes a new owner, who has to accept it with `accept_owner`
+    pub fn propose_owner(
+        ctx: Context<UpdatePresale>,
+        new_owner: Pubkey
+    ) -> ProgramResult {
         let presale_account = &mut ctx.accounts.presale_account;
 
         if presale_account.presale_owner != ctx.accounts.user_authority.key() {
             return Err(ErrorCode::NotRight.into())
         }
 
-        let spend_amount = ctx.accounts.pool_wen.amount;
-        if spend_amount < 1 {
-            return Err(ErrorCode::LowPoolWen.into())
+        if new_owner == Pubkey::default() || new_owner == presale_account.presale_owner {
+            return Err(ErrorCode::InvalidOwner.into())
         }
 
-        // Transfer WEN token from pool token account to user's token account.
-        {
-            // Transfer seeded from pool account to the user's account.
-            let presale_title = ctx.accounts.presale_account.presale_title.as_ref();
-            let seeds = &[
-                presale_title.trim_ascii_whitespace(),
-                &[ctx.accounts.presale_account.bumps.presale_account],
-            ];
-            let signer = &[&seeds[..]];
+        presale_account.pending_owner = new_owner;
 
-            let cpi_accounts = Transfer {
-                from: ctx.accounts.pool_wen.to_account_info(),
-                to: ctx.accounts.user_wen.to_account_info(),
-                authority: ctx.accounts.presale_account.to_account_info(),
-            };
-            let cpi_program = ctx.accounts.token_program.to_account_info();
-            let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer);
+        emit!(OwnerProposed {
+            presale: presale_account.key(),
+            owner: presale_account.presale_owner,
+            pending_owner: new_owner,
+            timestamp: Clock::get()?.unix_timestamp // Returns real-world time in second uint
+        });
+
+        Ok(())
+    }
+
+    // Proposed owner takes over the presale
+    pub fn accept_owner(
+        ctx: Context<UpdatePresale>
+    ) -> ProgramResult {
+        let presale_account = &mut ctx.accounts.presale_account;
+
+        if presale_account.pending_owner == Pubkey::default() {
+            return Err(ErrorCode::NoPendingOwner.into())
+        }
 
-            token::transfer(cpi_ctx, spend_amount)?;
+        if presale_account.pending_owner != ctx.accounts.user_authority.key() {
+            return Err(ErrorCode::NotRight.into())
         }
+
+        let previous_owner = presale_account.presale_owner;
+        presale_account.presale_owner = presale_account.pending_owner;
+        presale_account.pending_owner = Pubkey::default();
+
+        emit!(OwnerChanged {
+            presale: presale_account.key(),
+            previous_owner,
+            owner: presale_account.presale_owner,
+            timestamp: Clock::get()?.unix_timestamp // Returns real-world time in second uint
+        });
+
         Ok(())
     }
 
-    pub fn former_holders_config(
-        ctx: Context<FormerHoldersList>,
-        amount: u64
+    // Owner withdraws the ownership proposal
+    pub fn cancel_owner_proposal(
+        ctx: Context<UpdatePresale>
     ) -> ProgramResult {
-        let presale_account = &mut ctx.accounts.presale_account;       
-        let user_authority = ctx.accounts.user_authority.key();
-        if presale_account.presale_owner != user_authority {
+        let presale_account = &mut ctx.accounts.presale_account;
+
+        if presale_account.presale_owner != ctx.accounts.user_authority.key() {
             return Err(ErrorCode::NotRight.into())
         }
 
-        let clock = Clock::get()?; // Returns real-world time in second uint
+        if presale_account.pending_owner == Pubkey::default() {
+            return Err(ErrorCode::NoPendingOwner.into())
+        }
 
-        let user_account = &mut ctx.accounts.user_account;
-        user_account.locked_amount = amount;
-        user_account.last_deposit_ts = clock.unix_timestamp;
+        presale_account.pending_owner = Pubkey::default();
+
+        emit!(OwnerProposed {
+            presale: presale_account.key(),
+            owner: presale_account.presale_owner,
+            pending_owner: Pubkey::default(),
+            timestamp: Clock::get()?.unix_timestamp // Returns real-world time in second uint
+        });
 
         Ok(())
     }
-}
 
-#[derive(Accounts)]
-#[instruction(presale_title: String, bumps: PoolBumps)]
-pub struct Initialize<'info> {
-    // State Accounts
-    #[account(
-        init,
-        seeds = [presale_title.as_bytes()],
-        bump = bumps.presale_account,
-        payer = presale_owner
-    )]
-    pub presale_account: Account<'info, PresaleAccount>,
-    // Contract Authority accounts
-    #[account(mut)]
-    pub presale_owner: Signer<'info>,
-    // USDC Mint
-    #[account(constraint = usdc_mint.decimals == USDC_DECIMAL)]
-    pub usdc_mint: Account<'info, Mint>,
-    // WEN token Mint
-    #[account(constraint = wen_mint.decimals == WEN_DECIMAL)]
-    pub wen_mint: Account<'info, Mint>,
+    // Owner assigns a role to `authority` while multisig is off
+    pub fn grant_role(
+        ctx: Context<UpdatePresale>,
+        role: Role,
+        authority: Pubkey
+    ) -> ProgramResult {
+        let presale_account = &mut ctx.accounts.presale_account;
 
-    // USDC POOL
-    #[account(
+        if presale_account.presale_owner != ctx.accounts.user_authority.key() {
+            return Err(ErrorCode::NotRight.into())
+        }
+
+        // A granted role can move funds, so it needs a proposal under multisig
+        if presale_account.approval_threshold > 0 {
+            return Err(ErrorCode::MultisigRequired.into())
+        }
+
+        if authority == Pubkey::default() {
+            return Err(ErrorCode::InvalidRoleAuthority.into())
+        }
+
+        presale_account.set_role(role, authority);
+
+        emit!(RoleChanged {
+            presale: presale_account.key(),
+            role,
+            authority,
+            timestamp: Clock::get()?.unix_timestamp // Returns real-world time in second uint
+        });
+
+        Ok(())
+    }
+
+    // Owner removes a role, nobody holds it until granted again
+    pub fn revoke_role(
+        ctx: Context<UpdatePresale>,
+        role: Role
+    ) -> ProgramResult {
+        let presale_account = &mut ctx.accounts.presale_account;
+
+        if presale_account.presale_owner != ctx.accounts.user_authority.key() {
+            return Err(ErrorCode::NotRight.into())
+        }
+
+        presale_account.set_role(role, Pubkey::default());
+
+        emit!(RoleChanged {
+            presale: presale_account.key(),
+            role,
+            authority: Pubkey::default(),
+            timestamp: Clock::get()?.unix_timestamp // Returns real-world time in second uint
+        });
+
+        Ok(())
+    }
+
+    // Owner registers a token account that withdrawals can be sent to.
+    // While the list is empty any token account of the pool mint is allowed.
+    pub fn add_withdraw_destination(
+        ctx: Context<UpdatePresale>,
+        destination: Pubkey
+    ) -> ProgramResult {
+        let presale_account = &mut ctx.accounts.presale_account;
+
+        if presale_account.presale_owner != ctx.accounts.user_authority.key() {
+            return Err(ErrorCode::NotRight.into())
+        }
+
+        presale_account.add_withdraw_destination(destination)?;
+
+        emit!(WithdrawDestinationChanged {
+            presale: presale_account.key(),
+            destination,
+            added: true,
+            timestamp: Clock::get()?.unix_timestamp // Returns real-world time in second uint
+        });
+
+        Ok(())
+    }
+
+    // Owner removes a registered withdrawal destination
+    pub fn remove_withdraw_destination(
+        ctx: Context<UpdatePresale>,
+        destination: Pubkey
+    ) -> ProgramResult {
+        let presale_account = &mut ctx.accounts.presale_account;
+
+        if presale_account.presale_owner != ctx.accounts.user_authority.key() {
+            return Err(ErrorCode::NotRight.into())
+        }
+
+        presale_account.remove_withdraw_destination(&destination)?;
+
+        emit!(WithdrawDestinationChanged {
+            presale: presale_account.key(),
+            destination,
+            added: false,
+            timestamp: Clock::get()?.unix_timestamp // Returns real-world time in second uint
+        });
+
+        Ok(())
+    }
+
+    // Operator adds a sale round with its own price, allocation and lock terms
+    pub fn create_round(
+        ctx: Context<CreateRound>,
+        bump: u8,
+        params: RoundParams
+    ) -> ProgramResult {
+        let presale_account = &mut ctx.accounts.presale_account;
+
+        if presale_account.operator != ctx.accounts.user_authority.key() {
+            return Err(ErrorCode::NotRight.into())
+        }
+
+        // Rounds set the sale price, which needs a proposal while multisig is on
+        if presale_account.approval_threshold > 0 {
+            return Err(ErrorCode::MultisigRequired.into())
+        }
+
+        let clock = Clock::get()?; // Returns real-world time in second uint
+        presale_account.require_status(clock.unix_timestamp, &[PresaleStatus::Pending, PresaleStatus::Active])?;
+
+        if presale_account.round_count >= MAX_ROUNDS {
+            return Err(ErrorCode::TooManyRounds.into())
+        }
+
+        if params.token_price < 1 {
+            return Err(ErrorCode::InvalidPrice.into())
+        }
+
+        if params.allocation < 1 {
+            return Err(ErrorCode::InvalidRound.into())
+        }
+
+        validate_lock_terms(params.lock_rate, params.lock_duration)?;
+
+        let round_account = &mut ctx.accounts.round_account;
+        round_account.presale = presale_account.key();
+        round_account.index = presale_account.round_count;
+        round_account.bump = bump;
+        round_account.token_price = params.token_price;
+        round_account.allocation = params.allocation;
+        round_account.lock_rate = params.lock_rate;
+        round_account.lock_duration = params.lock_duration;
+
+        presale_account.round_count = presale_account.round_count
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(RoundCreated {
+            presale: presale_account.key(),
+            round: round_account.key(),
+            index: round_account.index,
+            token_price: params.token_price,
+            allocation: params.allocation,
+            lock_rate: params.lock_rate,
+            lock_duration: params.lock_duration,
+            timestamp: clock.unix_timestamp
+        });
+
+        Ok(())
+    }
+
+    // Operator switches the round used by `purchase_in_round`
+    pub fn set_active_round(
+        ctx: Context<SetActiveRound>
+    ) -> ProgramResult {
+        let presale_account = &mut ctx.accounts.presale_account;
+
+        if presale_account.operator != ctx.accounts.user_authority.key() {
+            return Err(ErrorCode::NotRight.into())
+        }
+
+        if presale_account.approval_threshold > 0 {
+            return Err(ErrorCode::MultisigRequired.into())
+        }
+
+        let clock = Clock::get()?; // Returns real-world time in second uint
+        presale_account.require_status(clock.unix_timestamp, &[PresaleStatus::Pending, PresaleStatus::Active])?;
+
+        presale_account.active_round = ctx.accounts.round_account.index;
+
+        emit!(ActiveRoundChanged {
+            presale: presale_account.key(),
+            round: ctx.accounts.round_account.key(),
+            index: presale_account.active_round,
+            timestamp: clock.unix_timestamp
+        });
+
+        Ok(())
+    }
+
+    // Init user account
+    pub fn init_user_account(
+        ctx: Context<InitUserAccount>, 
+        bump: u8
+    ) -> ProgramResult {
+        msg!("INIT USER INFO ACCOUNT {:?}", ctx.accounts.user_authority.key().to_string());
+
+        let user_account = &mut ctx.accounts.user_account;
+        user_account.owner = ctx.accounts.user_authority.key();
+        user_account.presale = ctx.accounts.presale_account.key();
+        user_account.bump = bump;
+
+        emit!(UserAccountChanged {
+            presale: user_account.presale,
+            user: user_account.owner,
+            user_account: user_account.key(),
+            closed: false,
+            timestamp: Clock::get()?.unix_timestamp // Returns real-world time in second uint
+        });
+
+        Ok(())
+    }
+
+    // stake SEEDED token into player
+    pub fn purchase(
+        ctx: Context<DepositUsdcForWenToken>,
+        amount: u64
+    ) -> ProgramResult {
+        msg!("Enter staking!!!");
+        if ctx.accounts.presale_account.active_round != NO_ACTIVE_ROUND {
+            return Err(ErrorCode::RoundRequired.into())
+        }
+
+        let token_price = ctx.accounts.presale_account.token_price;
+        let lock_rate = ctx.accounts.presale_account.lock_rate;
+        let lock_duration = ctx.accounts.presale_account.lock_duration;
+        process_purchase(ctx.accounts, amount, token_price, lock_rate, lock_duration)?;
+
+        Ok(())
+    }
+
+    // Purchase WEN token with the price and lock terms of the active round
+    pub fn purchase_in_round(
+        ctx: Context<PurchaseInRound>,
+        amount: u64
+    ) -> ProgramResult {
+        let round_account = &ctx.accounts.round_account;
+        if round_account.index != ctx.accounts.base.presale_account.active_round {
+            return Err(ErrorCode::RoundNotActive.into())
+        }
+
+        // Total USDC accepted in the round can't exceed its allocation
+        let raised = round_account.raised
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        if raised > round_account.allocation {
+            return Err(ErrorCode::RoundAllocationReached.into())
+        }
+
+        let token_price = round_account.token_price;
+        let lock_rate = round_account.lock_rate;
+        let lock_duration = round_account.lock_duration;
+        let index = round_account.index as usize;
+        let wen_amount = process_purchase(&mut ctx.accounts.base, amount, token_price, lock_rate, lock_duration)?;
+
+        let round_account = &mut ctx.accounts.round_account;
+        round_account.raised = raised;
+
+        let user_account = &mut ctx.accounts.base.user_account;
+        user_account.round_purchases[index] = user_account.round_purchases[index]
+            .checked_add(wen_amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        user_account.round_deposits[index] = user_account.round_deposits[index]
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        Ok(())
+    }
+
+    // In deferred delivery mode, buyers claim the unlocked WEN token
+    // once the sale is finalized and TGE has passed
+    pub fn claim(
+        ctx: Context<ClaimLockedWenToken>
+    ) -> ProgramResult {
+        let presale_account = &ctx.accounts.presale_account;
+        let spend_amount = ctx.accounts.user_account.pending_amount;
+
+        if presale_account.claims_paused {
+            return Err(ErrorCode::Paused.into())
+        }
+
+        if spend_amount < 1 {
+            return Err(ErrorCode::NotEnoughClaim.into())
+        }
+
+        let clock = Clock::get()?; // Returns real-world time in second uint
+        presale_account.require_status(clock.unix_timestamp, &[PresaleStatus::Finalized])?;
+
+        if presale_account.tge_ts == 0 || clock.unix_timestamp < presale_account.tge_ts {
+            return Err(ErrorCode::NotAbleClaim.into())
+        }
+
+        if ctx.accounts.pool_wen.amount < spend_amount {
+            return Err(ErrorCode::LowPoolWen.into())
+        }
+
+        // Transfer WEN token from pool token account to user's token account.
+        transfer_from_pool(
+            &ctx.accounts.presale_account,
+            &ctx.accounts.pool_wen,
+            ctx.accounts.user_wen.to_account_info(),
+            &ctx.accounts.token_program,
+            spend_amount
+        )?;
+
+        // update info
+        let user_account = &mut ctx.accounts.user_account;
+        user_account.pending_amount = 0;
+        user_account.unlocked_amount = user_account.unlocked_amount
+            .checked_add(spend_amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        let presale_account = &mut ctx.accounts.presale_account;
+        presale_account.reserved_wen = presale_account.reserved_wen.saturating_sub(spend_amount);
+        presale_account.total_claimed_wen = presale_account.total_claimed_wen
+            .checked_add(spend_amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(Claimed {
+            presale: presale_account.key(),
+            user: ctx.accounts.user_authority.key(),
+            amount: spend_amount,
+            locked: false,
+            timestamp: clock.unix_timestamp
+        });
+
+        Ok(())
+    }
+
+    pub fn claim_locked_wen(
+        ctx: Context<ClaimLockedWenToken>
+    ) -> ProgramResult {        
+        let user_account = &mut ctx.accounts.user_account;
+        let locked_amount = user_account.locked_amount;
+        let claimed_amount = user_account.claimed_amount;
+
+        if locked_amount <= claimed_amount {
+            return Err(ErrorCode::NotEnoughClaim.into())
+        }
+
+        let clock = Clock::get()?; // Returns real-world time in second uint
+        let presale_account = &ctx.accounts.presale_account;
+        if presale_account.claims_paused {
+            return Err(ErrorCode::Paused.into())
+        }
+
+        // Claimed WEN can't be taken back on refund, so nothing unlocks before the sale is finalized
+        presale_account.require_status(clock.unix_timestamp, &[PresaleStatus::Finalized])?;
+
+        // Lock is measured from TGE if the presale says so,
+        // and deferred delivery keeps all WEN in the pool until TGE
+        let wait_for_tge = presale_account.lock_from_tge || presale_account.delivery_mode == DeliveryMode::Deferred;
+        if wait_for_tge
+            && (presale_account.tge_ts == 0 || clock.unix_timestamp < presale_account.tge_ts) {
+            return Err(ErrorCode::NotAbleClaim.into())
+        }
+
+        // Each tranche unlocks on its own schedule,
+        // only vested but unclaimed tokens are paid out
+        let mut spend_amount: u64 = 0;
+        for tranche in user_account.tranches.iter_mut() {
+            let lock_start_ts = if presale_account.lock_from_tge {
+                presale_account.tge_ts
+            } else {
+                tranche.start_ts
+            };
+            let vested_amount = calculate_vested_amount(
+                tranche.amount,
+                lock_start_ts + tranche.lock_duration,
+                presale_account.vesting_period,
+                clock.unix_timestamp
+            );
+            spend_amount = spend_amount
+                .checked_add(vested_amount.saturating_sub(tranche.claimed_amount))
+                .ok_or(ErrorCode::MathOverflow)?;
+            tranche.claimed_amount = tranche.claimed_amount.max(vested_amount);
+        }
+        if spend_amount < 1 {
+            return Err(ErrorCode::NotAbleClaim.into())
+        }
+
+        if ctx.accounts.pool_wen.amount < spend_amount {
+            return Err(ErrorCode::LowPoolWen.into())
+        }
+
+        // Transfer WEN token from pool token account to user's token account.
+        transfer_from_pool(
+            &ctx.accounts.presale_account,
+            &ctx.accounts.pool_wen,
+            ctx.accounts.user_wen.to_account_info(),
+            &ctx.accounts.token_program,
+            spend_amount
+        )?;
+
+        // update info
+        user_account.claimed_amount = claimed_amount
+            .checked_add(spend_amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        let presale_account = &mut ctx.accounts.presale_account;
+        presale_account.reserved_wen = presale_account.reserved_wen.saturating_sub(spend_amount);
+        presale_account.total_claimed_wen = presale_account.total_claimed_wen
+            .checked_add(spend_amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(Claimed {
+            presale: presale_account.key(),
+            user: ctx.accounts.user_authority.key(),
+            amount: spend_amount,
+            locked: true,
+            timestamp: clock.unix_timestamp
+        });
+
+        Ok(())
+
+    }
+
+    // If the presale is cancelled or the soft cap was not reached, buyers get their USDC back.
+    // WEN received at purchase has to be returned and locked WEN is forfeited.
+    pub fn refund(
+        ctx: Context<RefundUsdc>
+    ) -> ProgramResult {
+        let presale_account = &ctx.accounts.presale_account;
+
+        // Refunds ignore `claims_paused` so the operator can't freeze buyers' USDC
+        let clock = Clock::get()?; // Returns real-world time in second uint
+        match presale_account.current_status(clock.unix_timestamp) {
+            PresaleStatus::Cancelled => {},
+            PresaleStatus::Ended if presale_account.total_raised < presale_account.soft_cap => {},
+            PresaleStatus::Pending | PresaleStatus::Active => {
+                return Err(ErrorCode::SaleNotEnded.into())
+            },
+            _ => return Err(ErrorCode::RefundNotAvailable.into())
+        }
+
+        let refund_amount = ctx.accounts.user_account.deposit_amount;
+        if refund_amount < 1 {
+            return Err(ErrorCode::NothingToRefund.into())
+        }
+
+        if ctx.accounts.pool_usdc.amount < refund_amount {
+            return Err(ErrorCode::LowPoolUSDC.into())
+        }
+
+        // Return WEN token received at purchase to the pool.
+        let return_amount = ctx.accounts.user_account.unlocked_amount;
+        if return_amount > 0 {
+            check_user_wen(&ctx.accounts.user_wen, &ctx.accounts.user_authority.key(), &ctx.accounts.wen_mint.key())?;
+
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.user_wen.to_account_info(),
+                to: ctx.accounts.pool_wen.to_account_info(),
+                authority: ctx.accounts.user_authority.to_account_info(),
+            };
+
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+            token::transfer(cpi_ctx, return_amount)?;
+        }
+
+        // Transfer USDC from pool token account to user's token account.
+        transfer_from_pool(
+            &ctx.accounts.presale_account,
+            &ctx.accounts.pool_usdc,
+            ctx.accounts.user_usdc.to_account_info(),
+            &ctx.accounts.token_program,
+            refund_amount
+        )?;
+
+        // Forfeited WEN is no longer reserved
+        let forfeited_amount = ctx.accounts.user_account.locked_amount
+            .saturating_sub(ctx.accounts.user_account.claimed_amount)
+            + ctx.accounts.user_account.pending_amount;
+        let presale_account = &mut ctx.accounts.presale_account;
+        presale_account.reserved_wen = presale_account.reserved_wen.saturating_sub(forfeited_amount);
+        presale_account.record_refund(&ctx.accounts.user_account);
+
+        // Refunded USDC leaves the rounds it was paid in,
+        // the round accounts are passed as remaining accounts
+        let user_account = &mut ctx.accounts.user_account;
+        for account_info in ctx.remaining_accounts.iter() {
+            let mut round_account: Account<RoundAccount> = Account::try_from(account_info)?;
+            if round_account.presale != presale_account.key() {
+                return Err(ErrorCode::InvalidRound.into())
+            }
+            let index = round_account.index as usize;
+            round_account.raised = round_account.raised.saturating_sub(user_account.round_deposits[index]);
+            user_account.round_deposits[index] = 0;
+            round_account.exit(ctx.program_id)?;
+        }
+        if user_account.round_deposits.iter().any(|deposit| *deposit > 0) {
+            return Err(ErrorCode::RoundAccountMissing.into())
+        }
+
+        // update info
+        user_account.participated = false;
+        user_account.allocated_amount = 0;
+        user_account.deposit_amount = 0;
+        user_account.unlocked_amount = 0;
+        user_account.pending_amount = 0;
+        user_account.locked_amount = 0;
+        user_account.claimed_amount = 0;
+        user_account.round_purchases = Default::default();
+        user_account.tranches = Default::default();
+
+        emit!(Refunded {
+            presale: ctx.accounts.presale_account.key(),
+            user: ctx.accounts.user_authority.key(),
+            amount: refund_amount,
+            returned: return_amount,
+            timestamp: clock.unix_timestamp
+        });
+
+        Ok(())
+    }
+
+    // User closes the info account and gets its rent back once nothing is owed to them,
+    // also after the presale account itself was closed
+    pub fn close_user_account(
+        ctx: Context<CloseUserAccount>
+    ) -> ProgramResult {
+        // A closed presale was finalized, its user accounts can always be closed
+        let presale_info = &ctx.accounts.presale_account;
+        let clock = Clock::get()?; // Returns real-world time in second uint
+        let presale_status = if presale_info.owner == ctx.program_id && presale_info.lamports() > 0 {
+            let presale_account: Account<PresaleAccount> = Account::try_from(presale_info)?;
+            Some(presale_account.current_status(clock.unix_timestamp))
+        } else {
+            None
+        };
+
+        ctx.accounts.user_account.require_closable(presale_status)?;
+
+        emit!(UserAccountChanged {
+            presale: presale_info.key(),
+            user: ctx.accounts.user_authority.key(),
+            user_account: ctx.accounts.user_account.key(),
+            closed: true,
+            timestamp: clock.unix_timestamp
+        });
+
+        Ok(())
+    }
+
+    // After presale, treasurer can withdraw the raised USDC
+    pub fn withdraw_usdc(
+        ctx: Context<WithdrawUSDC>,
+        amount: u64
+    ) -> ProgramResult {        
+        let presale_account = &mut ctx.accounts.presale_account;
+        
+
+        if presale_account.treasurer != ctx.accounts.user_authority.key() {
+            return Err(ErrorCode::NotRight.into())
+        }
+
+        if presale_account.approval_threshold > 0 {
+            return Err(ErrorCode::MultisigRequired.into())
+        }
+
+        if presale_account.withdrawal_delay > 0 {
+            return Err(ErrorCode::TimelockRequired.into())
+        }
+
+        presale_account.require_withdraw_destination(&ctx.accounts.destination.key())?;
+
+        // Raised USDC stays in the pool for refunds until the sale is finalized
+        let clock = Clock::get()?; // Returns real-world time in second uint
+        presale_account.require_status(clock.unix_timestamp, &[PresaleStatus::Finalized])?;
+
+        if amount < 1 {
+            return Err(ErrorCode::InvalidAmount.into())
+        }
+
+        if ctx.accounts.pool_usdc.amount < amount {
+            return Err(ErrorCode::LowPoolUSDC.into())
+        }
+
+        let fee = withdraw_from_pool(
+            presale_account,
+            PoolAsset::Usdc,
+            &ctx.accounts.pool_usdc,
+            ctx.accounts.destination.to_account_info(),
+            &ctx.accounts.fee_recipient,
+            &ctx.accounts.token_program,
+            amount
+        )?;
+
+        emit!(Withdrawn {
+            presale: presale_account.key(),
+            authority: ctx.accounts.user_authority.key(),
+            asset: PoolAsset::Usdc,
+            destination: ctx.accounts.destination.key(),
+            amount,
+            fee,
+            timestamp: clock.unix_timestamp
+        });
+
+        Ok(())
+    }
+
+    // After presale, treasurer can withdraw the remaining tokens
+    pub fn withdraw_wen(
+        ctx: Context<WithdrawWenToken>,
+        amount: u64
+    ) -> ProgramResult {        
+        let presale_account = &ctx.accounts.presale_account;
+
+        if presale_account.treasurer != ctx.accounts.user_authority.key() {
+            return Err(ErrorCode::NotRight.into())
+        }
+
+        if presale_account.approval_threshold > 0 {
+            return Err(ErrorCode::MultisigRequired.into())
+        }
+
+        if presale_account.withdrawal_delay > 0 {
+            return Err(ErrorCode::TimelockRequired.into())
+        }
+
+        presale_account.require_withdraw_destination(&ctx.accounts.destination.key())?;
+
+        let clock = Clock::get()?; // Returns real-world time in second uint
+        presale_account.require_status(clock.unix_timestamp, &[PresaleStatus::Finalized, PresaleStatus::Cancelled])?;
+
+        if amount < 1 {
+            return Err(ErrorCode::InvalidAmount.into())
+        }
+
+        // WEN owed to buyers stays in the pool
+        if presale_account.available_wen(ctx.accounts.pool_wen.amount, clock.unix_timestamp) < amount {
+            return Err(ErrorCode::LowPoolWen.into())
+        }
+
+        transfer_from_pool(
+            presale_account,
+            &ctx.accounts.pool_wen,
+            ctx.accounts.destination.to_account_info(),
+            &ctx.accounts.token_program,
+            amount
+        )?;
+
+        emit!(Withdrawn {
+            presale: presale_account.key(),
+            authority: ctx.accounts.user_authority.key(),
+            asset: PoolAsset::Wen,
+            destination: ctx.accounts.destination.key(),
+            amount,
+            fee: 0,
+            timestamp: clock.unix_timestamp
+        });
+
+        Ok(())
+    }
+
+    // Owner closes a finalized presale once both pools are empty, rent goes back to the owner
+    pub fn close_presale(
+        ctx: Context<ClosePresale>
+    ) -> ProgramResult {
+        let presale_account = &ctx.accounts.presale_account;
+
+        if presale_account.presale_owner != ctx.accounts.user_authority.key() {
+            return Err(ErrorCode::NotRight.into())
+        }
+
+        let clock = Clock::get()?; // Returns real-world time in second uint
+        presale_account.require_closable(ctx.accounts.pool_usdc.amount, ctx.accounts.pool_wen.amount, clock.unix_timestamp)?;
+
+        close_pool(presale_account, &ctx.accounts.pool_usdc, ctx.accounts.user_authority.to_account_info(), &ctx.accounts.token_program)?;
+        close_pool(presale_account, &ctx.accounts.pool_wen, ctx.accounts.user_authority.to_account_info(), &ctx.accounts.token_program)?;
+
+        emit!(PresaleClosed {
+            presale: presale_account.key(),
+            owner: presale_account.presale_owner,
+            timestamp: clock.unix_timestamp
+        });
+
+        Ok(())
+    }
+
+    // Owner registers multisig admins while multisig is off.
+    // Once `threshold` > 0, withdrawals, price/limit and TGE changes, role grants and allocations
+    // need approved proposals, rounds are frozen and the admin set itself can only be changed by a proposal.
+    pub fn set_admins(
+        ctx: Context<UpdatePresale>,
+        admins: Vec<Pubkey>,
+        threshold: u8
+    ) -> ProgramResult {
+        let presale_account = &mut ctx.accounts.presale_account;
+
+        if presale_account.presale_owner != ctx.accounts.user_authority.key() {
+            return Err(ErrorCode::NotRight.into())
+        }
+
+        if presale_account.approval_threshold > 0 {
+            return Err(ErrorCode::MultisigRequired.into())
+        }
+
+        presale_account.apply_admins(&admins, threshold)?;
+
+        emit!(AdminsChanged {
+            presale: presale_account.key(),
+            admin_count: presale_account.admin_count,
+            threshold,
+            admin_version: presale_account.admin_version,
+            timestamp: Clock::get()?.unix_timestamp // Returns real-world time in second uint
+        });
+
+        Ok(())
+    }
+
+    // Admin proposes a withdrawal or config change, proposer's approval is counted
+    pub fn create_proposal(
+        ctx: Context<CreateProposal>,
+        bump: u8,
+        action: ProposalAction
+    ) -> ProgramResult {
+        let presale_account = &mut ctx.accounts.presale_account;
+
+        if presale_account.approval_threshold < 1 {
+            return Err(ErrorCode::MultisigDisabled.into())
+        }
+
+        let admin_index = presale_account
+            .admin_index(&ctx.accounts.user_authority.key())
+            .ok_or(ErrorCode::NotRight)?;
+
+        // Withdrawal delay runs from approval, so withdrawals are only proposed once they are possible
+        let clock = Clock::get()?; // Returns real-world time in second uint
+        if let ProposalAction::Withdraw { asset, .. } = action {
+            presale_account.require_withdraw_status(asset, clock.unix_timestamp)?;
+        }
+
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.presale = presale_account.key();
+        proposal.id = presale_account.proposal_count;
+        proposal.proposer = ctx.accounts.user_authority.key();
+        proposal.action = action;
+        proposal.approvals = 1 << admin_index;
+        proposal.admin_version = presale_account.admin_version;
+        proposal.executed = false;
+        proposal.bump = bump;
+
+        presale_account.record_approval(proposal, clock.unix_timestamp);
+
+        presale_account.proposal_count = presale_account.proposal_count
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(ProposalCreated {
+            presale: presale_account.key(),
+            proposal: proposal.key(),
+            id: proposal.id,
+            proposer: proposal.proposer,
+            action,
+            timestamp: clock.unix_timestamp
+        });
+
+        Ok(())
+    }
+
+    // Admin approves a proposal
+    pub fn approve_proposal(
+        ctx: Context<ApproveProposal>
+    ) -> ProgramResult {
+        let presale_account = &ctx.accounts.presale_account;
+        let proposal = &mut ctx.accounts.proposal;
+
+        let admin_index = presale_account
+            .admin_index(&ctx.accounts.user_authority.key())
+            .ok_or(ErrorCode::NotRight)?;
+
+        if proposal.executed || proposal.admin_version != presale_account.admin_version {
+            return Err(ErrorCode::ProposalClosed.into())
+        }
+
+        proposal.approvals |= 1 << admin_index;
+
+        let clock = Clock::get()?; // Returns real-world time in second uint
+        presale_account.record_approval(proposal, clock.unix_timestamp);
+
+        emit!(ProposalApproved {
+            presale: presale_account.key(),
+            proposal: proposal.key(),
+            id: proposal.id,
+            admin: ctx.accounts.user_authority.key(),
+            approvals: proposal.approvals,
+            approved: proposal.approved_ts > 0,
+            timestamp: clock.unix_timestamp
+        });
+
+        Ok(())
+    }
+
+    // Admin executes an approved config proposal
+    pub fn execute_proposal(
+        ctx: Context<ApproveProposal>
+    ) -> ProgramResult {
+        let presale_account = &mut ctx.accounts.presale_account;
+        let proposal = &mut ctx.accounts.proposal;
+
+        if presale_account.admin_index(&ctx.accounts.user_authority.key()).is_none() {
+            return Err(ErrorCode::NotRight.into())
+        }
+
+        presale_account.require_approved(proposal)?;
+
+        let clock = Clock::get()?; // Returns real-world time in second uint
+        match proposal.action {
+            ProposalAction::SetTokenPrice { token_price } => {
+                presale_account.apply_token_price(token_price, clock.unix_timestamp)?;
+
+                emit!(TokenPriceChanged {
+                    presale: presale_account.key(),
+                    token_price,
+                    timestamp: clock.unix_timestamp
+                });
+            },
+            ProposalAction::SetContributionLimits { min_contribution, max_contribution } => {
+                presale_account.apply_contribution_limits(min_contribution, max_contribution, clock.unix_timestamp)?;
+
+                emit!(ContributionLimitsChanged {
+                    presale: presale_account.key(),
+                    min_contribution,
+                    max_contribution,
+                    timestamp: clock.unix_timestamp
+                });
+            },
+            ProposalAction::SetAdmins { admins, admin_count, threshold } => {
+                if admin_count as usize > MAX_ADMINS {
+                    return Err(ErrorCode::InvalidAdmins.into())
+                }
+                presale_account.apply_admins(&admins[..admin_count as usize], threshold)?;
+
+                emit!(AdminsChanged {
+                    presale: presale_account.key(),
+                    admin_count,
+                    threshold,
+                    admin_version: presale_account.admin_version,
+                    timestamp: clock.unix_timestamp
+                });
+            },
+            ProposalAction::SetTge { tge_ts } => {
+                presale_account.apply_tge(tge_ts, clock.unix_timestamp)?;
+
+                emit!(TgeChanged {
+                    presale: presale_account.key(),
+                    tge_ts,
+                    timestamp: clock.unix_timestamp
+                });
+            },
+            ProposalAction::GrantRole { role, authority } => {
+                if authority == Pubkey::default() {
+                    return Err(ErrorCode::InvalidRoleAuthority.into())
+                }
+                presale_account.set_role(role, authority);
+
+                emit!(RoleChanged {
+                    presale: presale_account.key(),
+                    role,
+                    authority,
+                    timestamp: clock.unix_timestamp
+                });
+            },
+            ProposalAction::Withdraw { .. } | ProposalAction::Allocate { .. } => {
+                return Err(ErrorCode::WrongProposalKind.into())
+            }
+        }
+
+        proposal.executed = true;
+
+        emit!(ProposalExecuted {
+            presale: presale_account.key(),
+            proposal: proposal.key(),
+            id: proposal.id,
+            executor: ctx.accounts.user_authority.key(),
+            timestamp: clock.unix_timestamp
+        });
+
+        Ok(())
+    }
+
+    // Admin executes an approved withdrawal proposal
+    pub fn execute_withdraw_proposal(
+        ctx: Context<ExecuteWithdrawProposal>
+    ) -> ProgramResult {
+        let presale_account = &mut ctx.accounts.presale_account;
+
+        if presale_account.admin_index(&ctx.accounts.user_authority.key()).is_none() {
+            return Err(ErrorCode::NotRight.into())
+        }
+
+        presale_account.require_approved(&ctx.accounts.proposal)?;
+
+        let (asset, amount, destination) = match ctx.accounts.proposal.action {
+            ProposalAction::Withdraw { asset, amount, destination } => (asset, amount, destination),
+            _ => return Err(ErrorCode::WrongProposalKind.into())
+        };
+
+        if ctx.accounts.destination.key() != destination {
+            return Err(ErrorCode::InvalidDestination.into())
+        }
+
+        presale_account.require_withdraw_destination(&destination)?;
+
+        // Approved withdrawals wait for the withdrawal delay too
+        let clock = Clock::get()?; // Returns real-world time in second uint
+        presale_account.require_delay_passed(ctx.accounts.proposal.approved_ts, clock.unix_timestamp)?;
+
+        presale_account.require_withdrawable(asset, &ctx.accounts.pool.key(), clock.unix_timestamp)?;
+
+        // WEN owed to buyers stays in the pool
+        let available_amount = match asset {
+            PoolAsset::Usdc => ctx.accounts.pool.amount,
+            PoolAsset::Wen => presale_account.available_wen(ctx.accounts.pool.amount, clock.unix_timestamp)
+        };
+        if amount < 1 || available_amount < amount {
+            return Err(ErrorCode::InvalidAmount.into())
+        }
+
+        let fee = withdraw_from_pool(
+            presale_account,
+            asset,
+            &ctx.accounts.pool,
+            ctx.accounts.destination.to_account_info(),
+            &ctx.accounts.fee_recipient,
+            &ctx.accounts.token_program,
+            amount
+        )?;
+
+        emit!(Withdrawn {
+            presale: presale_account.key(),
+            authority: ctx.accounts.user_authority.key(),
+            asset,
+            destination,
+            amount,
+            fee,
+            timestamp: clock.unix_timestamp
+        });
+
+        ctx.accounts.proposal.executed = true;
+
+        emit!(ProposalExecuted {
+            presale: presale_account.key(),
+            proposal: ctx.accounts.proposal.key(),
+            id: ctx.accounts.proposal.id,
+            executor: ctx.accounts.user_authority.key(),
+            timestamp: clock.unix_timestamp
+        });
+
+        Ok(())
+    }
+
+    // Admin executes an approved former holder allocation
+    pub fn execute_allocation_proposal(
+        ctx: Context<ExecuteAllocationProposal>
+    ) -> ProgramResult {
+        let presale_account = &mut ctx.accounts.presale_account;
+
+        if presale_account.admin_index(&ctx.accounts.user_authority.key()).is_none() {
+            return Err(ErrorCode::NotRight.into())
+        }
+
+        presale_account.require_approved(&ctx.accounts.proposal)?;
+
+        let (user, amount) = match ctx.accounts.proposal.action {
+            ProposalAction::Allocate { user, amount } => (user, amount),
+            _ => return Err(ErrorCode::WrongProposalKind.into())
+        };
+
+        let user_account = &mut ctx.accounts.user_account;
+        if user_account.owner != user {
+            return Err(ErrorCode::InvalidUserAccount.into())
+        }
+
+        let clock = Clock::get()?; // Returns real-world time in second uint
+        presale_account.allocate(user_account, amount, ctx.accounts.pool_wen.amount, clock.unix_timestamp)?;
+
+        emit!(FormerHolderAllocated {
+            presale: presale_account.key(),
+            user,
+            amount,
+            lock_duration: presale_account.lock_duration,
+            timestamp: clock.unix_timestamp
+        });
+
+        ctx.accounts.proposal.executed = true;
+
+        emit!(ProposalExecuted {
+            presale: presale_account.key(),
+            proposal: ctx.accounts.proposal.key(),
+            id: ctx.accounts.proposal.id,
+            executor: ctx.accounts.user_authority.key(),
+            timestamp: clock.unix_timestamp
+        });
+
+        Ok(())
+    }
+
+    // Treasurer announces a withdrawal, it can be executed once `withdrawal_delay` has passed
+    pub fn schedule_withdrawal(
+        ctx: Context<ScheduleWithdrawal>,
+        bump: u8,
+        asset: PoolAsset,
+        amount: u64,
+        destination: Pubkey
+    ) -> ProgramResult {
+        let presale_account = &mut ctx.accounts.presale_account;
+
+        if presale_account.treasurer != ctx.accounts.user_authority.key() {
+            return Err(ErrorCode::NotRight.into())
+        }
+
+        if presale_account.approval_threshold > 0 {
+            return Err(ErrorCode::MultisigRequired.into())
+        }
+
+        if amount < 1 {
+            return Err(ErrorCode::InvalidAmount.into())
+        }
+
+        presale_account.require_withdraw_destination(&destination)?;
+
+        // Delay only starts once withdrawals are possible, so buyers get the full announcement window
+        let clock = Clock::get()?; // Returns real-world time in second uint
+        presale_account.require_withdraw_status(asset, clock.unix_timestamp)?;
+
+        let withdrawal = &mut ctx.accounts.withdrawal;
+        withdrawal.presale = presale_account.key();
+        withdrawal.id = presale_account.withdrawal_count;
+        withdrawal.asset = asset;
+        withdrawal.amount = amount;
+        withdrawal.destination = destination;
+        withdrawal.executable_ts = clock.unix_timestamp + presale_account.withdrawal_delay;
+        withdrawal.bump = bump;
+
+        presale_account.withdrawal_count = presale_account.withdrawal_count
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(WithdrawalScheduled {
+            presale: presale_account.key(),
+            withdrawal: withdrawal.key(),
+            id: withdrawal.id,
+            asset,
+            amount,
+            destination,
+            executable_ts: withdrawal.executable_ts,
+            timestamp: clock.unix_timestamp
+        });
+
+        Ok(())
+    }
+
+    // Treasurer executes a scheduled withdrawal after its delay
+    pub fn execute_withdrawal(
+        ctx: Context<ExecuteWithdrawal>
+    ) -> ProgramResult {
+        let presale_account = &mut ctx.accounts.presale_account;
+        let withdrawal = &ctx.accounts.withdrawal;
+
+        if presale_account.treasurer != ctx.accounts.user_authority.key() {
+            return Err(ErrorCode::NotRight.into())
+        }
+
+        // Requests scheduled before multisig was turned on can't bypass it
+        if presale_account.approval_threshold > 0 {
+            return Err(ErrorCode::MultisigRequired.into())
+        }
+
+        let clock = Clock::get()?; // Returns real-world time in second uint
+        if clock.unix_timestamp < withdrawal.executable_ts {
+            return Err(ErrorCode::WithdrawalLocked.into())
+        }
+
+        if ctx.accounts.destination.key() != withdrawal.destination {
+            return Err(ErrorCode::InvalidDestination.into())
+        }
+
+        // Destination may have been removed from the whitelist since scheduling
+        presale_account.require_withdraw_destination(&withdrawal.destination)?;
+
+        presale_account.require_withdrawable(withdrawal.asset, &ctx.accounts.pool.key(), clock.unix_timestamp)?;
+
+        // WEN owed to buyers stays in the pool
+        let available_amount = match withdrawal.asset {
+            PoolAsset::Usdc => ctx.accounts.pool.amount,
+            PoolAsset::Wen => presale_account.available_wen(ctx.accounts.pool.amount, clock.unix_timestamp)
+        };
+        if available_amount < withdrawal.amount {
+            return Err(ErrorCode::InvalidAmount.into())
+        }
+
+        let fee = withdraw_from_pool(
+            presale_account,
+            withdrawal.asset,
+            &ctx.accounts.pool,
+            ctx.accounts.destination.to_account_info(),
+            &ctx.accounts.fee_recipient,
+            &ctx.accounts.token_program,
+            withdrawal.amount
+        )?;
+
+        emit!(Withdrawn {
+            presale: presale_account.key(),
+            authority: ctx.accounts.user_authority.key(),
+            asset: withdrawal.asset,
+            destination: withdrawal.destination,
+            amount: withdrawal.amount,
+            fee,
+            timestamp: clock.unix_timestamp
+        });
+
+        Ok(())
+    }
+
+    // Treasurer drops a scheduled withdrawal
+    pub fn cancel_withdrawal(
+        ctx: Context<CancelWithdrawal>
+    ) -> ProgramResult {
+        if ctx.accounts.presale_account.treasurer != ctx.accounts.user_authority.key() {
+            return Err(ErrorCode::NotRight.into())
+        }
+
+        emit!(WithdrawalCancelled {
+            presale: ctx.accounts.presale_account.key(),
+            withdrawal: ctx.accounts.withdrawal.key(),
+            id: ctx.accounts.withdrawal.id,
+            timestamp: Clock::get()?.unix_timestamp // Returns real-world time in second uint
+        });
+
+        Ok(())
+    }
+
+    pub fn former_holders_config(
+        ctx: Context<FormerHoldersList>,
+        amount: u64
+    ) -> ProgramResult {
+        let presale_account = &mut ctx.accounts.presale_account;       
+        let user_authority = ctx.accounts.user_authority.key();
+        if presale_account.allocation_manager != user_authority {
+            return Err(ErrorCode::NotRight.into())
+        }
+
+        // Allocations take WEN from the pool, so they need a proposal under multisig
+        if presale_account.approval_threshold > 0 {
+            return Err(ErrorCode::MultisigRequired.into())
+        }
+
+        let clock = Clock::get()?; // Returns real-world time in second uint
+        let user_account = &mut ctx.accounts.user_account;
+        presale_account.allocate(user_account, amount, ctx.accounts.pool_wen.amount, clock.unix_timestamp)?;
+
+        emit!(FormerHolderAllocated {
+            presale: presale_account.key(),
+            user: user_account.owner,
+            amount,
+            lock_duration: presale_account.lock_duration,
+            timestamp: clock.unix_timestamp
+        });
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(presale_id: u64, name: String, bumps: PoolBumps)]
+pub struct Initialize<'info> {
+    // State Accounts
+    #[account(
+        init,
+        seeds = [b"presale".as_ref(), presale_owner.key().as_ref(), &presale_id.to_le_bytes()],
+        bump = bumps.presale_account,
+        payer = presale_owner
+    )]
+    pub presale_account: Account<'info, PresaleAccount>,
+    // Contract Authority accounts
+    #[account(mut)]
+    pub presale_owner: Signer<'info>,
+    // Launchpad config, counts presales for the registry
+    #[account(
+        mut,
+        seeds = [b"config".as_ref()],
+        bump = global_config.bump
+    )]
+    pub global_config: Box<Account<'info, GlobalConfig>>,
+    // Registry entry of the new presale
+    #[account(
+        init,
+        seeds = [b"registry".as_ref(), &global_config.presale_count.to_le_bytes()],
+        bump = bumps.registry_entry,
+        payer = presale_owner
+    )]
+    pub registry_entry: Box<Account<'info, RegistryEntry>>,
+    // Launchpad wallet receiving the creation fee
+    #[account(
+        mut,
+        constraint = fee_wallet.key() == global_config.fee_wallet
+    )]
+    pub fee_wallet: UncheckedAccount<'info>,
+    // USDC Mint
+    #[account(constraint = usdc_mint.decimals == USDC_DECIMAL)]
+    pub usdc_mint: Account<'info, Mint>,
+    // WEN token Mint
+    #[account(constraint = wen_mint.decimals == WEN_DECIMAL)]
+    pub wen_mint: Account<'info, Mint>,
+
+    // USDC POOL
+    #[account(
+        init,
+        token::mint = usdc_mint,
+        token::authority = presale_account,
+        seeds = [presale_account.key().as_ref(), b"pool_usdc".as_ref()],
+        bump = bumps.pool_usdc,
+        payer = presale_owner
+    )]
+    pub pool_usdc: Account<'info, TokenAccount>,
+    // WEN token POOL
+    #[account(
+        init,
+        token::mint = wen_mint,
+        token::authority = presale_account,
+        seeds = [presale_account.key().as_ref(), b"pool_wen".as_ref()],
+        bump = bumps.pool_wen,
+        payer = presale_owner
+    )]
+    pub pool_wen: Account<'info, TokenAccount>,
+
+    // Programs and Sysvars
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>
+}
+
+#[derive(Accounts)]
+#[instruction(bump: u8)]
+pub struct InitGlobalConfig<'info> {
+    // Launchpad admin wallet
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        init,
+        seeds = [b"config".as_ref()],
+        bump = bump,
+        payer = admin
+    )]
+    pub global_config: Box<Account<'info, GlobalConfig>>,
+
+    // Program data of this program, holds the upgrade authority
+    pub program_data: Account<'info, ProgramData>,
+
+    // Programs and Sysvars
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateGlobalConfig<'info> {
+    // Launchpad admin or proposed admin wallet
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"config".as_ref()],
+        bump = global_config.bump
+    )]
+    pub global_config: Box<Account<'info, GlobalConfig>>,
+}
+
+#[derive(Accounts)]
+#[instruction(bump: u8)]
+pub struct InitUserAccount<'info> {
+    // State account for each user/wallet
+    #[account(
+        init,
+        seeds = [b"user".as_ref(), presale_account.key().as_ref(), user_authority.key().as_ref()],
+        bump = bump,
+        payer = user_authority
+    )]
+    pub user_account: Box<Account<'info, UserInfoAccount>>,
+    pub presale_account: Account<'info, PresaleAccount>,
+    // Contract Authority accounts
+    #[account(mut)]
+    pub user_authority: Signer<'info>,
+    // Programs and Sysvars
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct DepositUsdcForWenToken<'info> {
+    // Payable account (User wallet)
+    #[account(mut)]
+    pub user_authority: Signer<'info>,
+    // User's info
+    #[account(
+        mut,
+        constraint = user_account.owner == user_authority.key(),
+        constraint = user_account.presale == presale_account.key()
+    )]
+    pub user_account: Box<Account<'info, UserInfoAccount>>,
+    // TODO replace these with the ATA constraints when possible. 
+    // User's USDC token account
+    #[account(
+        mut,
+        constraint = user_usdc.owner ==user_authority.key(),
+        constraint = user_usdc.mint == usdc_mint.key()
+    )]
+    pub user_usdc: Account<'info, TokenAccount>,
+    // USD coin
+    #[account(mut)]
+    pub usdc_mint: Account<'info, Mint>,
+
+    // User's WEN token account, not needed in deferred delivery mode
+    #[account(mut)]
+    pub user_wen: UncheckedAccount<'info>,
+    // WEN token
+    #[account(mut)]
+    pub wen_mint: Account<'info, Mint>,
+    
+    #[account(
+        mut,
+        seeds = [b"presale".as_ref(), presale_account.creator.as_ref(), &presale_account.presale_id.to_le_bytes()],
+        bump = presale_account.bumps.presale_account,
+        has_one = usdc_mint
+    )]
+    pub presale_account: Box<Account<'info, PresaleAccount>>,
+    
+    // Pool for USDC and WEN
+    #[account(
+        mut,
+        seeds = [presale_account.key().as_ref(), b"pool_usdc".as_ref()],
+        bump = presale_account.bumps.pool_usdc)]
+    pub pool_usdc: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [presale_account.key().as_ref(), b"pool_wen".as_ref()],
+        bump = presale_account.bumps.pool_wen
+    )]
+    pub pool_wen: Account<'info, TokenAccount>,
+    
+    // Programs and Sysvars
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct PurchaseInRound<'info> {
+    pub base: DepositUsdcForWenToken<'info>,
+    // Active round of the presale
+    #[account(
+        mut,
+        constraint = round_account.presale == base.presale_account.key()
+    )]
+    pub round_account: Box<Account<'info, RoundAccount>>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimLockedWenToken<'info> {
+    // Payable account (User wallet)
+    #[account(mut)]
+    pub user_authority: Signer<'info>,
+    // User's info
+    #[account(
+        mut,
+        constraint = user_account.owner == user_authority.key(),
+        constraint = user_account.presale == presale_account.key()
+    )]
+    pub user_account: Box<Account<'info, UserInfoAccount>>,
+
+    // User's WEN token account
+    #[account(
+        mut,
+        constraint = user_wen.owner ==user_authority.key(),
+        constraint = user_wen.mint == wen_mint.key()
+    )]
+    pub user_wen: Account<'info, TokenAccount>,
+    // WEN token
+    #[account(mut)]
+    pub wen_mint: Account<'info, Mint>,
+    
+    #[account(
+        mut,
+        seeds = [presale_account.key().as_ref(), b"pool_wen".as_ref()],
+        bump = presale_account.bumps.pool_wen)]
+    pub pool_wen: Account<'info, TokenAccount>,
+    
+    #[account(
+        mut,
+        seeds = [b"presale".as_ref(), presale_account.creator.as_ref(), &presale_account.presale_id.to_le_bytes()],
+        bump = presale_account.bumps.presale_account,
+        has_one = wen_mint)]
+    pub presale_account: Box<Account<'info, PresaleAccount>>,
+    
+    // Programs and Sysvars
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct RefundUsdc<'info> {
+    // Payable account (User wallet)
+    #[account(mut)]
+    pub user_authority: Signer<'info>,
+    // User's info
+    #[account(
+        mut,
+        constraint = user_account.owner == user_authority.key(),
+        constraint = user_account.presale == presale_account.key()
+    )]
+    pub user_account: Box<Account<'info, UserInfoAccount>>,
+    // User's USDC token account
+    #[account(
+        mut,
+        constraint = user_usdc.owner ==user_authority.key(),
+        constraint = user_usdc.mint == usdc_mint.key()
+    )]
+    pub user_usdc: Account<'info, TokenAccount>,
+    // USD coin
+    pub usdc_mint: Account<'info, Mint>,
+
+    // User's WEN token account, only needed to return WEN token
+    #[account(mut)]
+    pub user_wen: UncheckedAccount<'info>,
+    // WEN token
+    pub wen_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [b"presale".as_ref(), presale_account.creator.as_ref(), &presale_account.presale_id.to_le_bytes()],
+        bump = presale_account.bumps.presale_account,
+        has_one = usdc_mint,
+        has_one = wen_mint
+    )]
+    pub presale_account: Box<Account<'info, PresaleAccount>>,
+
+    // Pool for USDC and WEN
+    #[account(
+        mut,
+        seeds = [presale_account.key().as_ref(), b"pool_usdc".as_ref()],
+        bump = presale_account.bumps.pool_usdc)]
+    pub pool_usdc: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [presale_account.key().as_ref(), b"pool_wen".as_ref()],
+        bump = presale_account.bumps.pool_wen
+    )]
+    pub pool_wen: Account<'info, TokenAccount>,
+
+    // Programs and Sysvars
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawUSDC<'info> {
+    // Payable account (User wallet)
+    #[account(mut)]
+    pub user_authority: Signer<'info>,
+    // Any USDC token account, see `withdraw_destinations`
+    #[account(
+        mut,
+        constraint = destination.mint == usdc_mint.key()
+    )]
+    pub destination: Account<'info, TokenAccount>,
+    
+    // Pool for USDC 
+    #[account(
+        mut,
+        seeds = [presale_account.key().as_ref(), b"pool_usdc".as_ref()],
+        bump = presale_account.bumps.pool_usdc
+    )]
+    pub pool_usdc: Account<'info, TokenAccount>,
+    // Launchpad config, its fee wallet gets the platform fee
+    #[account(
+        seeds = [b"config".as_ref()],
+        bump = global_config.bump
+    )]
+    pub global_config: Box<Account<'info, GlobalConfig>>,
+    // Platform fee account
+    #[account(
+        mut,
+        constraint = fee_recipient.mint == presale_account.usdc_mint,
+        constraint = fee_recipient.owner == global_config.fee_wallet
+    )]
+    pub fee_recipient: Account<'info, TokenAccount>,
+
+    // USD coin
+    #[account(mut)]
+    pub usdc_mint: Account<'info, Mint>,
+    
+    #[account(
+        mut,
+        seeds = [b"presale".as_ref(), presale_account.creator.as_ref(), &presale_account.presale_id.to_le_bytes()],
+        bump = presale_account.bumps.presale_account,
+        has_one = usdc_mint
+    )]
+    pub presale_account: Box<Account<'info, PresaleAccount>>,
+    
+    // Programs and Sysvars
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+
+// NOTE: we need to validate the owner
+#[derive(Accounts)]
+pub struct WithdrawWenToken<'info> {
+    // Payable account (User wallet)
+    #[account(mut)]
+    pub user_authority: Signer<'info>,
+
+    // Any WEN token account, see `withdraw_destinations`
+    #[account(
+        mut,
+        constraint = destination.mint == wen_mint.key()
+    )]
+    pub destination: Account<'info, TokenAccount>,
+    
+    #[account(mut,
+        seeds = [presale_account.key().as_ref(), b"pool_wen".as_ref()],
+        bump = presale_account.bumps.pool_wen)]
+    pub pool_wen: Account<'info, TokenAccount>,
+
+    // WEN token
+    #[account(mut)]
+    pub wen_mint: Account<'info, Mint>,
+    
+    #[account(
+        seeds = [b"presale".as_ref(), presale_account.creator.as_ref(), &presale_account.presale_id.to_le_bytes()],
+        bump = presale_account.bumps.presale_account,
+        has_one = wen_mint
+    )]
+    pub presale_account: Box<Account<'info, PresaleAccount>>,
+    
+    // Programs and Sysvars
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct CloseUserAccount<'info> {
+    // User wallet, receives the rent
+    #[account(mut)]
+    pub user_authority: Signer<'info>,
+
+    #[account(
+        mut,
+        close = user_authority,
+        constraint = user_account.owner == user_authority.key(),
+        constraint = user_account.presale == presale_account.key()
+    )]
+    pub user_account: Box<Account<'info, UserInfoAccount>>,
+
+    // Presale of the user account, may already be closed
+    pub presale_account: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClosePresale<'info> {
+    // Owner wallet, receives the rent
+    #[account(mut)]
+    pub user_authority: Signer<'info>,
+
+    #[account(
+        mut,
+        close = user_authority,
+        seeds = [b"presale".as_ref(), presale_account.creator.as_ref(), &presale_account.presale_id.to_le_bytes()],
+        bump = presale_account.bumps.presale_account
+    )]
+    pub presale_account: Box<Account<'info, PresaleAccount>>,
+
+    #[account(
+        mut,
+        seeds = [presale_account.key().as_ref(), b"pool_usdc".as_ref()],
+        bump = presale_account.bumps.pool_usdc)]
+    pub pool_usdc: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [presale_account.key().as_ref(), b"pool_wen".as_ref()],
+        bump = presale_account.bumps.pool_wen
+    )]
+    pub pool_wen: Account<'info, TokenAccount>,
+
+    // Programs and Sysvars
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePresale<'info> {
+    // Owner, role holder or proposed owner wallet
+    pub user_authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"presale".as_ref(), presale_account.creator.as_ref(), &presale_account.presale_id.to_le_bytes()],
+        bump = presale_account.bumps.presale_account
+    )]
+    pub presale_account: Box<Account<'info, PresaleAccount>>,
+}
+
+#[derive(Accounts)]
+#[instruction(bump: u8)]
+pub struct CreateMetadata<'info> {
+    // Owner wallet
+    #[account(mut)]
+    pub user_authority: Signer<'info>,
+
+    #[account(
         init,
-        token::mint = usdc_mint,
-        token::authority = presale_account,
-        seeds = [presale_title.as_bytes(), b"pool_usdc".as_ref()],
-        bump = bumps.pool_usdc,
-        payer = presale_owner
+        seeds = [b"metadata".as_ref(), presale_account.key().as_ref()],
+        bump = bump,
+        payer = user_authority,
+        space = PresaleMetadata::LEN
+    )]
+    pub metadata: Box<Account<'info, PresaleMetadata>>,
+
+    #[account(
+        seeds = [b"presale".as_ref(), presale_account.creator.as_ref(), &presale_account.presale_id.to_le_bytes()],
+        bump = presale_account.bumps.presale_account
+    )]
+    pub presale_account: Box<Account<'info, PresaleAccount>>,
+
+    // Programs and Sysvars
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateMetadata<'info> {
+    // Owner wallet
+    pub user_authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"metadata".as_ref(), presale_account.key().as_ref()],
+        bump = metadata.bump
+    )]
+    pub metadata: Box<Account<'info, PresaleMetadata>>,
+
+    #[account(
+        seeds = [b"presale".as_ref(), presale_account.creator.as_ref(), &presale_account.presale_id.to_le_bytes()],
+        bump = presale_account.bumps.presale_account
+    )]
+    pub presale_account: Box<Account<'info, PresaleAccount>>,
+}
+
+#[derive(Accounts)]
+#[instruction(bump: u8)]
+pub struct CreateRound<'info> {
+    // Operator wallet
+    #[account(mut)]
+    pub user_authority: Signer<'info>,
+
+    #[account(
+        init,
+        seeds = [b"round".as_ref(), presale_account.key().as_ref(), &[presale_account.round_count]],
+        bump = bump,
+        payer = user_authority
+    )]
+    pub round_account: Box<Account<'info, RoundAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"presale".as_ref(), presale_account.creator.as_ref(), &presale_account.presale_id.to_le_bytes()],
+        bump = presale_account.bumps.presale_account
+    )]
+    pub presale_account: Box<Account<'info, PresaleAccount>>,
+
+    // Programs and Sysvars
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct SetActiveRound<'info> {
+    // Operator wallet
+    pub user_authority: Signer<'info>,
+
+    #[account(constraint = round_account.presale == presale_account.key())]
+    pub round_account: Box<Account<'info, RoundAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"presale".as_ref(), presale_account.creator.as_ref(), &presale_account.presale_id.to_le_bytes()],
+        bump = presale_account.bumps.presale_account
+    )]
+    pub presale_account: Box<Account<'info, PresaleAccount>>,
+}
+
+#[derive(Accounts)]
+#[instruction(bump: u8)]
+pub struct CreateProposal<'info> {
+    // Admin wallet
+    #[account(mut)]
+    pub user_authority: Signer<'info>,
+
+    #[account(
+        init,
+        seeds = [b"proposal".as_ref(), presale_account.key().as_ref(), &presale_account.proposal_count.to_le_bytes()],
+        bump = bump,
+        payer = user_authority,
+        space = Proposal::LEN
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+
+    #[account(
+        mut,
+        seeds = [b"presale".as_ref(), presale_account.creator.as_ref(), &presale_account.presale_id.to_le_bytes()],
+        bump = presale_account.bumps.presale_account
+    )]
+    pub presale_account: Box<Account<'info, PresaleAccount>>,
+
+    // Programs and Sysvars
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveProposal<'info> {
+    // Admin wallet
+    pub user_authority: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = proposal.presale == presale_account.key()
     )]
-    pub pool_usdc: Account<'info, TokenAccount>,
-    // WEN token POOL
+    pub proposal: Box<Account<'info, Proposal>>,
+
+    #[account(
+        mut,
+        seeds = [b"presale".as_ref(), presale_account.creator.as_ref(), &presale_account.presale_id.to_le_bytes()],
+        bump = presale_account.bumps.presale_account
+    )]
+    pub presale_account: Box<Account<'info, PresaleAccount>>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteWithdrawProposal<'info> {
+    // Admin wallet
+    pub user_authority: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = proposal.presale == presale_account.key()
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+
+    #[account(
+        mut,
+        seeds = [b"presale".as_ref(), presale_account.creator.as_ref(), &presale_account.presale_id.to_le_bytes()],
+        bump = presale_account.bumps.presale_account
+    )]
+    pub presale_account: Box<Account<'info, PresaleAccount>>,
+
+    // USDC or WEN pool of the presale
+    #[account(mut)]
+    pub pool: Account<'info, TokenAccount>,
+    // Token account from the proposal
+    #[account(mut)]
+    pub destination: Account<'info, TokenAccount>,
+    // Launchpad config, its fee wallet gets the platform fee
+    #[account(
+        seeds = [b"config".as_ref()],
+        bump = global_config.bump
+    )]
+    pub global_config: Box<Account<'info, GlobalConfig>>,
+    // Platform fee account, only used for USDC withdrawals
+    #[account(
+        mut,
+        constraint = fee_recipient.mint == presale_account.usdc_mint,
+        constraint = fee_recipient.owner == global_config.fee_wallet
+    )]
+    pub fee_recipient: Account<'info, TokenAccount>,
+
+    // Programs and Sysvars
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteAllocationProposal<'info> {
+    // Admin wallet
+    pub user_authority: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = proposal.presale == presale_account.key()
+    )]
+    pub proposal: Box<Account<'info, Proposal>>,
+
+    #[account(
+        mut,
+        seeds = [b"presale".as_ref(), presale_account.creator.as_ref(), &presale_account.presale_id.to_le_bytes()],
+        bump = presale_account.bumps.presale_account
+    )]
+    pub presale_account: Box<Account<'info, PresaleAccount>>,
+
+    // Info account of the former holder from the proposal
+    #[account(
+        mut,
+        constraint = user_account.presale == presale_account.key()
+    )]
+    pub user_account: Box<Account<'info, UserInfoAccount>>,
+
+    // Pool for WEN, must cover allocations
+    #[account(
+        seeds = [presale_account.key().as_ref(), b"pool_wen".as_ref()],
+        bump = presale_account.bumps.pool_wen
+    )]
+    pub pool_wen: Account<'info, TokenAccount>,
+}
+
+#[derive(Accounts)]
+#[instruction(bump: u8)]
+pub struct ScheduleWithdrawal<'info> {
+    // Treasurer wallet
+    #[account(mut)]
+    pub user_authority: Signer<'info>,
+
     #[account(
         init,
-        token::mint = wen_mint,
-        token::authority = presale_account,
-        seeds = [presale_title.as_bytes(), b"pool_wen".as_ref()],
-        bump = bumps.pool_wen,
-        payer = presale_owner
+        seeds = [b"withdrawal".as_ref(), presale_account.key().as_ref(), &presale_account.withdrawal_count.to_le_bytes()],
+        bump = bump,
+        payer = user_authority
+    )]
+    pub withdrawal: Box<Account<'info, WithdrawalRequest>>,
+
+    #[account(
+        mut,
+        seeds = [b"presale".as_ref(), presale_account.creator.as_ref(), &presale_account.presale_id.to_le_bytes()],
+        bump = presale_account.bumps.presale_account
+    )]
+    pub presale_account: Box<Account<'info, PresaleAccount>>,
+
+    // Programs and Sysvars
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteWithdrawal<'info> {
+    // Treasurer wallet
+    #[account(mut)]
+    pub user_authority: Signer<'info>,
+
+    // Executed withdrawal is closed and rent goes back to the treasurer
+    #[account(
+        mut,
+        close = user_authority,
+        constraint = withdrawal.presale == presale_account.key()
+    )]
+    pub withdrawal: Box<Account<'info, WithdrawalRequest>>,
+
+    #[account(
+        mut,
+        seeds = [b"presale".as_ref(), presale_account.creator.as_ref(), &presale_account.presale_id.to_le_bytes()],
+        bump = presale_account.bumps.presale_account
+    )]
+    pub presale_account: Box<Account<'info, PresaleAccount>>,
+
+    // USDC or WEN pool of the presale
+    #[account(mut)]
+    pub pool: Account<'info, TokenAccount>,
+    // Token account from the scheduled withdrawal
+    #[account(mut)]
+    pub destination: Account<'info, TokenAccount>,
+    // Launchpad config, its fee wallet gets the platform fee
+    #[account(
+        seeds = [b"config".as_ref()],
+        bump = global_config.bump
+    )]
+    pub global_config: Box<Account<'info, GlobalConfig>>,
+    // Platform fee account, only used for USDC withdrawals
+    #[account(
+        mut,
+        constraint = fee_recipient.mint == presale_account.usdc_mint,
+        constraint = fee_recipient.owner == global_config.fee_wallet
+    )]
+    pub fee_recipient: Account<'info, TokenAccount>,
+
+    // Programs and Sysvars
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct CancelWithdrawal<'info> {
+    // Treasurer wallet
+    #[account(mut)]
+    pub user_authority: Signer<'info>,
+
+    #[account(
+        mut,
+        close = user_authority,
+        constraint = withdrawal.presale == presale_account.key()
+    )]
+    pub withdrawal: Box<Account<'info, WithdrawalRequest>>,
+
+    #[account(
+        seeds = [b"presale".as_ref(), presale_account.creator.as_ref(), &presale_account.presale_id.to_le_bytes()],
+        bump = presale_account.bumps.presale_account
+    )]
+    pub presale_account: Box<Account<'info, PresaleAccount>>,
+}
+
+#[derive(Accounts)]
+pub struct FormerHoldersList<'info> {
+    // Payable account (Allocation manager wallet)
+    #[account(mut)]
+    pub user_authority: Signer<'info>,
+    // User's info
+    #[account(
+        mut,
+        constraint = user_account.presale == presale_account.key()
+    )]
+    pub user_account: Box<Account<'info, UserInfoAccount>>,
+    
+    #[account(
+        mut,
+        seeds = [b"presale".as_ref(), presale_account.creator.as_ref(), &presale_account.presale_id.to_le_bytes()],
+        bump = presale_account.bumps.presale_account
+    )]
+    pub presale_account: Box<Account<'info, PresaleAccount>>,
+
+    // Pool for WEN, must cover allocations
+    #[account(
+        seeds = [presale_account.key().as_ref(), b"pool_wen".as_ref()],
+        bump = presale_account.bumps.pool_wen
     )]
     pub pool_wen: Account<'info, TokenAccount>,
+    
+    // Programs and Sysvars
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[account]
+#[derive(Default)]
+pub struct GlobalConfig {
+    pub admin: Pubkey, // launchpad admin
+    pub pending_admin: Pubkey, // proposed new admin, default while none
+    pub fee_wallet: Pubkey, // receives creation fees and owns fee token accounts
+    pub default_fee_bps: u64, // platform fee of new presales
+    pub creation_fee: u64, // lamports paid to create a presale
+    pub payment_mints: [Pubkey; MAX_PAYMENT_MINTS], // mints presales can raise in
+    pub payment_mint_count: u8,
+    pub presale_count: u64, // next registry id
+    pub bump: u8
+}
+
+impl GlobalConfig {
+    pub fn apply(&mut self, params: GlobalConfigParams) -> ProgramResult {
+        if params.fee_wallet == Pubkey::default() || params.payment_mints.len() > MAX_PAYMENT_MINTS {
+            return Err(ErrorCode::InvalidConfig.into())
+        }
+
+        if params.default_fee_bps > MAX_FEE_BPS {
+            return Err(ErrorCode::InvalidFee.into())
+        }
+
+        self.fee_wallet = params.fee_wallet;
+        self.default_fee_bps = params.default_fee_bps;
+        self.creation_fee = params.creation_fee;
+        self.payment_mints = [Pubkey::default(); MAX_PAYMENT_MINTS];
+        self.payment_mints[..params.payment_mints.len()].copy_from_slice(&params.payment_mints);
+        self.payment_mint_count = params.payment_mints.len() as u8;
+        Ok(())
+    }
+
+    pub fn is_payment_mint(&self, mint: &Pubkey) -> bool {
+        self.payment_mints[..self.payment_mint_count as usize].contains(mint)
+    }
+}
+
+// Presale of the launchpad registry, found by sequential id
+#[account]
+#[derive(Default)]
+pub struct RegistryEntry {
+    pub id: u64,
+    pub presale: Pubkey,
+    pub creator: Pubkey,
+    pub created_ts: i64,
+    pub bump: u8
+}
+
+#[account]
+#[derive(Default)]
+pub struct PresaleAccount {
+    pub creator: Pubkey, // presale PDA is derived from creator and id
+    pub presale_id: u64,
+    pub name: [u8; MAX_NAME_LEN], // human-readable name, zero padded
+    pub registry_id: u64, // id of the launchpad registry entry
+    pub bumps: PoolBumps,
+    pub presale_owner: Pubkey, // owner of program
+    pub pending_owner: Pubkey, // proposed new owner, default while none
+    pub treasurer: Pubkey, // withdraws funds
+    pub operator: Pubkey, // pauses and configures the sale
+    pub allocation_manager: Pubkey, // allocates WEN to former holders
+    pub usdc_mint: Pubkey,
+    pub wen_mint: Pubkey,
+    pub pool_usdc: Pubkey,
+    pub pool_wen: Pubkey,
+    pub token_price: u64, // USDC base units paid for one whole WEN token
+    pub start_ts: i64, // sale opens at this time
+    pub end_ts: i64, // sale closes at this time
+    pub soft_cap: u64, // USDC to raise for the sale to succeed
+    pub hard_cap: u64, // max USDC accepted
+    pub total_raised: u64, // USDC accepted so far
+    pub min_contribution: u64, // min USDC deposit per wallet
+    pub max_contribution: u64, // max USDC deposit per wallet
+    pub round_count: u8,
+    pub active_round: u8, // `NO_ACTIVE_ROUND` when sale uses presale terms
+    pub vesting_period: i64, // locked WEN is released linearly over this period after the lock ends
+    pub lock_rate: u64, // locked part of bought WEN in basis points
+    pub lock_duration: i64, // seconds
+    pub tge_ts: i64, // token generation event, 0 until set
+    pub lock_from_tge: bool, // locks start at TGE instead of purchase time
+    pub delivery_mode: DeliveryMode,
+    pub status: PresaleStatus, // only Finalized and Cancelled are stored, see `current_status`
+    pub purchases_paused: bool,
+    pub claims_paused: bool,
+    pub admins: [Pubkey; MAX_ADMINS], // multisig admins
+    pub admin_count: u8,
+    pub approval_threshold: u8, // approvals needed, 0 while multisig is off
+    pub admin_version: u32, // bumped when admins change, older proposals can't be executed
+    pub proposal_count: u64,
+    pub withdrawal_delay: i64, // seconds between scheduling and executing a withdrawal, 0 while off
+    pub withdrawal_count: u64,
+    pub reserved_wen: u64, // locked and deferred WEN owed to buyers, can't be withdrawn
+    pub withdraw_destinations: [Pubkey; MAX_WITHDRAW_DESTINATIONS], // withdrawal whitelist, unused while empty
+    pub withdraw_destination_count: u8,
+    pub fee_bps: u64, // platform fee on withdrawn USDC in basis points, taken from the launchpad config
+    pub fees_collected: u64, // USDC paid to the platform so far
+    pub total_unlocked_wen: u64, // unlocked WEN sold, delivered or pending
+    pub total_locked_wen: u64, // locked WEN sold
+    pub total_allocated_wen: u64, // locked WEN allocated to former holders
+    pub total_claimed_wen: u64, // pending and locked WEN claimed by users
+    pub participant_count: u64, // wallets with at least one purchase
+    pub first_purchase_ts: i64, // 0 until the first purchase
+    pub last_purchase_ts: i64
+}
+
+impl PresaleAccount {
+    // Finalized and Cancelled are set by the owner, other statuses follow the sale window
+    pub fn current_status(&self, now: i64) -> PresaleStatus {
+        match self.status {
+            PresaleStatus::Finalized | PresaleStatus::Cancelled => self.status,
+            _ if now < self.start_ts => PresaleStatus::Pending,
+            _ if now < self.end_ts => PresaleStatus::Active,
+            _ => PresaleStatus::Ended
+        }
+    }
+
+    pub fn apply_token_price(&mut self, token_price: u64, now: i64) -> ProgramResult {
+        if token_price < 1 {
+            return Err(ErrorCode::InvalidPrice.into())
+        }
+
+        self.require_not_started(now)?;
+
+        self.token_price = token_price;
+        Ok(())
+    }
+
+    pub fn apply_contribution_limits(&mut self, min_contribution: u64, max_contribution: u64, now: i64) -> ProgramResult {
+        if max_contribution < 1 || min_contribution > max_contribution {
+            return Err(ErrorCode::InvalidContributionLimits.into())
+        }
+
+        self.require_not_started(now)?;
+
+        self.min_contribution = min_contribution;
+        self.max_contribution = max_contribution;
+        Ok(())
+    }
+
+    // Replace multisig admins, `threshold` 0 turns multisig off
+    pub fn apply_admins(&mut self, admins: &[Pubkey], threshold: u8) -> ProgramResult {
+        if admins.len() > MAX_ADMINS || threshold as usize > admins.len() {
+            return Err(ErrorCode::InvalidAdmins.into())
+        }
+
+        for (i, admin) in admins.iter().enumerate() {
+            if *admin == Pubkey::default() || admins[..i].contains(admin) {
+                return Err(ErrorCode::InvalidAdmins.into())
+            }
+        }
+
+        self.admins = [Pubkey::default(); MAX_ADMINS];
+        self.admins[..admins.len()].copy_from_slice(admins);
+        self.admin_count = admins.len() as u8;
+        self.approval_threshold = threshold;
+        self.admin_version = self.admin_version
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    pub fn admin_index(&self, key: &Pubkey) -> Option<usize> {
+        self.admins[..self.admin_count as usize]
+            .iter()
+            .position(|admin| admin == key)
+    }
+
+    // Fails unless the proposal is open and has enough approvals from current admins
+    pub fn require_approved(&self, proposal: &Proposal) -> ProgramResult {
+        if self.approval_threshold < 1 {
+            return Err(ErrorCode::MultisigDisabled.into())
+        }
+
+        if proposal.executed || proposal.admin_version != self.admin_version {
+            return Err(ErrorCode::ProposalClosed.into())
+        }
+
+        if proposal.approvals.count_ones() < self.approval_threshold as u32 {
+            return Err(ErrorCode::NotEnoughApprovals.into())
+        }
+        Ok(())
+    }
+
+    // Start the withdrawal delay once the proposal gets enough approvals
+    pub fn record_approval(&self, proposal: &mut Proposal, now: i64) {
+        if proposal.approved_ts == 0 && proposal.approvals.count_ones() >= self.approval_threshold as u32 {
+            proposal.approved_ts = now;
+        }
+    }
+
+    // WEN in the pool that isn't owed to buyers.
+    // Nothing can be claimed from a cancelled presale, so everything is available then.
+    pub fn available_wen(&self, pool_amount: u64, now: i64) -> u64 {
+        if self.current_status(now) == PresaleStatus::Cancelled {
+            return pool_amount
+        }
+        pool_amount.saturating_sub(self.reserved_wen)
+    }
+
+    // Fails unless `pool` is the pool of `asset` and the presale status allows withdrawing it
+    pub fn require_withdrawable(&self, asset: PoolAsset, pool: &Pubkey, now: i64) -> ProgramResult {
+        let asset_pool = match asset {
+            PoolAsset::Usdc => self.pool_usdc,
+            PoolAsset::Wen => self.pool_wen
+        };
+        if *pool != asset_pool {
+            return Err(ErrorCode::InvalidPool.into())
+        }
+        self.require_withdraw_status(asset, now)
+    }
+
+    // Fails unless the presale status allows withdrawing `asset`
+    pub fn require_withdraw_status(&self, asset: PoolAsset, now: i64) -> ProgramResult {
+        match asset {
+            PoolAsset::Usdc => self.require_status(now, &[PresaleStatus::Finalized]),
+            PoolAsset::Wen => self.require_status(now, &[PresaleStatus::Finalized, PresaleStatus::Cancelled])
+        }
+    }
+
+    // Fails until `withdrawal_delay` has passed since `announced_ts`
+    pub fn require_delay_passed(&self, announced_ts: i64, now: i64) -> ProgramResult {
+        if now < announced_ts.saturating_add(self.withdrawal_delay) {
+            return Err(ErrorCode::WithdrawalLocked.into())
+        }
+        Ok(())
+    }
+
+    // Fails if destinations are registered and `destination` isn't one of them
+    pub fn require_withdraw_destination(&self, destination: &Pubkey) -> ProgramResult {
+        let destinations = &self.withdraw_destinations[..self.withdraw_destination_count as usize];
+        if !destinations.is_empty() && !destinations.contains(destination) {
+            return Err(ErrorCode::DestinationNotAllowed.into())
+        }
+        Ok(())
+    }
+
+    pub fn add_withdraw_destination(&mut self, destination: Pubkey) -> ProgramResult {
+        let count = self.withdraw_destination_count as usize;
+        if destination == Pubkey::default() || self.withdraw_destinations[..count].contains(&destination) {
+            return Err(ErrorCode::InvalidDestination.into())
+        }
+
+        if count >= MAX_WITHDRAW_DESTINATIONS {
+            return Err(ErrorCode::TooManyDestinations.into())
+        }
+
+        self.withdraw_destinations[count] = destination;
+        self.withdraw_destination_count = self.withdraw_destination_count
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    pub fn remove_withdraw_destination(&mut self, destination: &Pubkey) -> ProgramResult {
+        let count = self.withdraw_destination_count as usize;
+        let index = self.withdraw_destinations[..count]
+            .iter()
+            .position(|d| d == destination)
+            .ok_or(ErrorCode::InvalidDestination)?;
+
+        // Keep registered destinations packed at the front
+        self.withdraw_destinations[index] = self.withdraw_destinations[count - 1];
+        self.withdraw_destinations[count - 1] = Pubkey::default();
+        self.withdraw_destination_count -= 1;
+        Ok(())
+    }
+
+    // Update sale statistics with a purchase of `unlocked_amount` + `locked_amount` WEN
+    pub fn record_purchase(&mut self, first_purchase: bool, unlocked_amount: u64, locked_amount: u64, now: i64) -> ProgramResult {
+        self.total_unlocked_wen = self.total_unlocked_wen
+            .checked_add(unlocked_amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.total_locked_wen = self.total_locked_wen
+            .checked_add(locked_amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        if first_purchase {
+            self.participant_count = self.participant_count
+                .checked_add(1)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+        if self.first_purchase_ts == 0 {
+            self.first_purchase_ts = now;
+        }
+        self.last_purchase_ts = now;
+        Ok(())
+    }
+
+    // Take a refunded buyer out of the sale statistics.
+    // `locked_amount` is the locked WEN the buyer paid for, former holder allocations are `allocated_amount`.
+    pub fn record_refund(&mut self, user_account: &UserInfoAccount) {
+        let allocated_amount = user_account.allocated_amount;
+        let locked_amount = user_account.locked_amount.saturating_sub(allocated_amount);
+        let unlocked_amount = user_account.unlocked_amount.saturating_add(user_account.pending_amount);
+
+        self.total_raised = self.total_raised.saturating_sub(user_account.deposit_amount);
+        self.total_unlocked_wen = self.total_unlocked_wen.saturating_sub(unlocked_amount);
+        self.total_locked_wen = self.total_locked_wen.saturating_sub(locked_amount);
+        self.total_allocated_wen = self.total_allocated_wen.saturating_sub(allocated_amount);
+        if user_account.participated {
+            self.participant_count = self.participant_count.saturating_sub(1);
+        }
+    }
+
+    // Platform share of `amount` withdrawn USDC
+    pub fn platform_fee(&self, amount: u64) -> u64 {
+        (amount as u128 * self.fee_bps as u128 / BPS_DENOMINATOR as u128) as u64
+    }
+
+    pub fn set_role(&mut self, role: Role, authority: Pubkey) {
+        match role {
+            Role::Treasurer => self.treasurer = authority,
+            Role::Operator => self.operator = authority,
+            Role::AllocationManager => self.allocation_manager = authority
+        }
+    }
+
+    pub fn apply_tge(&mut self, tge_ts: i64, now: i64) -> ProgramResult {
+        self.require_status(now, &[
+            PresaleStatus::Pending,
+            PresaleStatus::Active,
+            PresaleStatus::Ended,
+            PresaleStatus::Finalized
+        ])?;
+
+        if self.tge_ts != 0 && now >= self.tge_ts {
+            return Err(ErrorCode::TgePassed.into())
+        }
 
-    // Programs and Sysvars
-    pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
-    pub rent: Sysvar<'info, Rent>
+        if tge_ts <= now || tge_ts < self.end_ts {
+            return Err(ErrorCode::InvalidTge.into())
+        }
+
+        self.tge_ts = tge_ts;
+        Ok(())
+    }
+
+    // Lock `amount` WEN for a former holder with the presale lock terms.
+    // `pool_amount` has to hold the allocation on top of WEN already owed to buyers.
+    pub fn allocate(&mut self, user_account: &mut UserInfoAccount, amount: u64, pool_amount: u64, now: i64) -> ProgramResult {
+        if amount < 1 {
+            return Err(ErrorCode::InvalidAmount.into())
+        }
+
+        self.require_status(now, &[
+            PresaleStatus::Pending,
+            PresaleStatus::Active,
+            PresaleStatus::Ended,
+            PresaleStatus::Finalized
+        ])?;
+
+        let reserved_wen = self.reserved_wen
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        if pool_amount < reserved_wen {
+            return Err(ErrorCode::LowPoolWen.into())
+        }
+
+        user_account.add_tranche(amount, now, self.lock_duration, self.lock_from_tge)?;
+        user_account.allocated_amount = user_account.allocated_amount
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        self.reserved_wen = reserved_wen;
+        self.total_allocated_wen = self.total_allocated_wen
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    // Fails unless the sale is finalized and nothing is left in the pools or owed to buyers
+    pub fn require_closable(&self, pool_usdc_amount: u64, pool_wen_amount: u64, now: i64) -> ProgramResult {
+        self.require_status(now, &[PresaleStatus::Finalized])?;
+
+        if pool_usdc_amount > 0 || pool_wen_amount > 0 || self.reserved_wen > 0 {
+            return Err(ErrorCode::PresaleNotEmpty.into())
+        }
+        Ok(())
+    }
+
+    // Fails unless the presale is in one of `allowed` statuses
+    pub fn require_status(&self, now: i64, allowed: &[PresaleStatus]) -> ProgramResult {
+        if !allowed.contains(&self.current_status(now)) {
+            return Err(ErrorCode::InvalidStatus.into())
+        }
+        Ok(())
+    }
+
+    // Run `f` with the signer seeds of the presale PDA, which owns both pools
+    pub fn with_signer<T>(&self, f: impl FnOnce(&[&[&[u8]]]) -> T) -> T {
+        let presale_id = self.presale_id.to_le_bytes();
+        let seeds = &[
+            b"presale".as_ref(),
+            self.creator.as_ref(),
+            presale_id.as_ref(),
+            &[self.bumps.presale_account],
+        ];
+        f(&[&seeds[..]])
+    }
+
+    // Sale terms can only change before the sale opens
+    pub fn require_not_started(&self, now: i64) -> ProgramResult {
+        match self.current_status(now) {
+            PresaleStatus::Pending => Ok(()),
+            PresaleStatus::Active | PresaleStatus::Ended => Err(ErrorCode::SaleStarted.into()),
+            _ => Err(ErrorCode::InvalidStatus.into())
+        }
+    }
 }
 
-#[derive(Accounts)]
-#[instruction(bump: u8, seed0: String, seed1: String)]
-pub struct InitUserAccount<'info> {
-    // State account for each user/wallet
-    #[account(
-        init,
-        seeds = [presale_account.presale_title.as_ref(), seed0.as_ref(), seed1.as_ref()],
-        bump = bump,
-        payer = user_authority
-    )]
-    pub user_account: Account<'info, UserInfoAccount>,
-    pub presale_account: Account<'info, PresaleAccount>,
-    // Contract Authority accounts
-    #[account(mut)]
-    pub user_authority: Signer<'info>,
-    // Programs and Sysvars
-    pub system_program: Program<'info, System>,
-    pub rent: Sysvar<'info, Rent>,
+#[account]
+pub struct Proposal {
+    pub presale: Pubkey,
+    pub id: u64,
+    pub proposer: Pubkey,
+    pub action: ProposalAction,
+    pub approvals: u16, // bit i is set when admin i approved
+    pub admin_version: u32, // admin set the approvals refer to
+    pub approved_ts: i64, // when approvals reached the threshold, 0 before
+    pub executed: bool,
+    pub bump: u8
 }
 
-#[derive(Accounts)]
-pub struct DepositUsdcForWenToken<'info> {
-    // Payable account (User wallet)
-    #[account(mut)]
-    pub user_authority: Signer<'info>,
-    // User's info
-    #[account(
-        mut,
-        constraint = user_account.owner == user_authority.key()
-    )]
-    pub user_account: Account<'info, UserInfoAccount>,
-    // TODO replace these with the ATA constraints when possible. 
-    // User's USDC token account
-    #[account(
-        constraint = user_usdc.owner ==user_authority.key(),
-        constraint = user_usdc.mint == usdc_mint.key()
-    )]
-    pub user_usdc: Account<'info, TokenAccount>,
-    // USD coin
-    #[account(mut)]
-    pub usdc_mint: Account<'info, Mint>,
+impl Proposal {
+    // Discriminator + fields, `SetAdmins` is the largest action
+    pub const LEN: usize = 8 + 32 + 8 + 32 + (1 + 32 * MAX_ADMINS + 1 + 1) + 2 + 4 + 8 + 1 + 1;
+}
 
-    // User's WEN token account
-    #[account(
-        mut,
-        constraint = user_wen.owner ==user_authority.key(),
-        constraint = user_wen.mint == wen_mint.key()
-    )]
-    pub user_wen: Account<'info, TokenAccount>,
-    // WEN token
-    #[account(mut)]
-    pub wen_mint: Account<'info, Mint>,
-    
-    #[account(
-        seeds = [presale_account.presale_title.as_ref().trim_ascii_whitespace()],
-        bump = presale_account.bumps.presale_account,
-        has_one = usdc_mint
-    )]
-    pub presale_account: Box<Account<'info, PresaleAccount>>,
-    
-    // Pool for USDC and WEN
-    #[account(
-        seeds = [presale_account.presale_title.as_ref().trim_ascii_whitespace(), b"pool_usdc".as_ref()],
-        bump = presale_account.bumps.pool_usdc)]
-    pub pool_usdc: Account<'info, TokenAccount>,
-    #[account(
-        seeds = [presale_account.presale_title.as_ref().trim_ascii_whitespace(), b"pool_wen".as_ref()],
-        bump = presale_account.bumps.pool_wen
-    )]
-    pub pool_wen: Account<'info, TokenAccount>,
-    
-    // Programs and Sysvars
-    pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
-    pub rent: Sysvar<'info, Rent>,
+#[account]
+#[derive(Default)]
+pub struct PresaleMetadata {
+    pub presale: Pubkey,
+    pub name: String, // display name
+    pub description: String,
+    pub website_uri: String,
+    pub whitepaper_uri: String,
+    pub image_uri: String,
+    pub symbol: String, // WEN token symbol
+    pub twitter_uri: String,
+    pub telegram_uri: String,
+    pub discord_uri: String,
+    pub bump: u8
 }
 
-#[derive(Accounts)]
-pub struct ClaimLockedWenToken<'info> {
-    // Payable account (User wallet)
-    #[account(mut)]
-    pub user_authority: Signer<'info>,
-    // User's info
-    #[account(
-        mut,
-        constraint = user_account.owner == user_authority.key()
-    )]
-    pub user_account: Account<'info, UserInfoAccount>,
+impl PresaleMetadata {
+    // Discriminator + fields, strings are sized at their max length
+    pub const LEN: usize = 8 + 32
+        + (4 + MAX_NAME_LEN)
+        + (4 + MAX_DESCRIPTION_LEN)
+        + (4 + MAX_URI_LEN) * 3
+        + (4 + MAX_SYMBOL_LEN)
+        + (4 + MAX_URI_LEN) * 3
+        + 1;
 
-    // User's WEN token account
-    #[account(
-        constraint = user_wen.owner ==user_authority.key(),
-        constraint = user_wen.mint == wen_mint.key()
-    )]
-    pub user_wen: Account<'info, TokenAccount>,
-    // WEN token
-    #[account(mut)]
-    pub wen_mint: Account<'info, Mint>,
-    
-    #[account(
-        seeds = [presale_account.presale_title.as_ref().trim_ascii_whitespace(), b"pool_wen".as_ref()],
-        bump = presale_account.bumps.pool_wen)]
-    pub pool_wen: Account<'info, TokenAccount>,
-    
-    #[account(
-        seeds = [presale_account.presale_title.as_ref().trim_ascii_whitespace()],
-        bump = presale_account.bumps.presale_account,
-        has_one = wen_mint)]
-    pub presale_account: Box<Account<'info, PresaleAccount>>,
-    
-    // Programs and Sysvars
-    pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
-    pub rent: Sysvar<'info, Rent>,
+    pub fn apply(&mut self, params: MetadataParams) {
+        self.name = params.name;
+        self.description = params.description;
+        self.website_uri = params.website_uri;
+        self.whitepaper_uri = params.whitepaper_uri;
+        self.image_uri = params.image_uri;
+        self.symbol = params.symbol;
+        self.twitter_uri = params.twitter_uri;
+        self.telegram_uri = params.telegram_uri;
+        self.discord_uri = params.discord_uri;
+    }
+}
+
+#[account]
+#[derive(Default)]
+pub struct WithdrawalRequest {
+    pub presale: Pubkey,
+    pub id: u64,
+    pub asset: PoolAsset,
+    pub amount: u64,
+    pub destination: Pubkey, // token account
+    pub executable_ts: i64,
+    pub bump: u8
+}
+
+#[account]
+#[derive(Default)]
+pub struct RoundAccount {
+    pub presale: Pubkey,
+    pub index: u8,
+    pub bump: u8,
+    pub token_price: u64, // USDC base units paid for one whole WEN token
+    pub allocation: u64, // max USDC accepted in this round
+    pub raised: u64, // USDC accepted in this round so far
+    pub lock_rate: u64, // locked part of bought WEN in basis points
+    pub lock_duration: i64 // seconds
+}
+
+#[account]
+#[derive(Default)]
+pub struct UserInfoAccount {
+    pub owner: Pubkey,
+    pub presale: Pubkey,
+    pub bump: u8,
+    pub deposit_amount: u64,
+    pub locked_amount: u64,
+    pub claimed_amount: u64, // locked WEN already claimed
+    pub unlocked_amount: u64, // unlocked WEN delivered to the user
+    pub pending_amount: u64, // unlocked WEN to claim in deferred delivery mode
+    pub last_deposit_ts: i64,
+    pub allocated_amount: u64, // locked WEN allocated as a former holder
+    pub participated: bool, // bought in the sale and wasn't refunded, counted in `participant_count`
+    pub round_purchases: [u64; MAX_ROUNDS as usize], // WEN bought in each round
+    pub round_deposits: [u64; MAX_ROUNDS as usize], // USDC paid in each round
+    pub tranches: [LockTranche; MAX_TRANCHES] // locked WEN of each lock schedule
+}
+
+impl UserInfoAccount {
+    // Fails while WEN is owed to the user.
+    // Buyers count towards contribution limits and participants, and may still be refunded,
+    // so their account stays until the sale is finalized. `presale_status` is None once the presale is closed.
+    pub fn require_closable(&self, presale_status: Option<PresaleStatus>) -> ProgramResult {
+        if self.locked_amount > self.claimed_amount || self.pending_amount > 0 {
+            return Err(ErrorCode::UserAccountNotEmpty.into())
+        }
+
+        if self.participated || self.deposit_amount > 0 {
+            match presale_status {
+                None | Some(PresaleStatus::Finalized) => {},
+                _ => return Err(ErrorCode::InvalidStatus.into())
+            }
+        }
+        Ok(())
+    }
+
+    // Lock `amount` WEN token in a tranche slot.
+    // It's merged into a tranche with the same schedule, all tranches start at TGE when `lock_from_tge`,
+    // otherwise it takes a free slot and fully claimed tranches are reused.
+    pub fn add_tranche(&mut self, amount: u64, start_ts: i64, lock_duration: i64, lock_from_tge: bool) -> ProgramResult {
+        let same_schedule = self.tranches
+            .iter_mut()
+            .find(|tranche| tranche.amount > 0
+                && tranche.lock_duration == lock_duration
+                && (lock_from_tge || tranche.start_ts == start_ts));
+
+        if let Some(tranche) = same_schedule {
+            tranche.amount = tranche.amount
+                .checked_add(amount)
+                .ok_or(ErrorCode::MathOverflow)?;
+        } else {
+            let tranche = self.tranches
+                .iter_mut()
+                .find(|tranche| tranche.amount == tranche.claimed_amount)
+                .ok_or(ErrorCode::TooManyTranches)?;
+
+            *tranche = LockTranche {
+                amount,
+                claimed_amount: 0,
+                start_ts,
+                lock_duration
+            };
+        }
+
+        self.locked_amount = self.locked_amount
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Default, Clone, Copy)]
+pub struct LockTranche {
+    pub amount: u64, // locked WEN
+    pub claimed_amount: u64,
+    pub start_ts: i64, // lock starts at this time
+    pub lock_duration: i64 // seconds
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Default, Clone)]
+pub struct PresaleParams {
+    pub token_price: u64,
+    pub start_ts: i64,
+    pub end_ts: i64,
+    pub soft_cap: u64,
+    pub hard_cap: u64,
+    pub min_contribution: u64,
+    pub max_contribution: u64,
+    pub vesting_period: i64,
+    pub lock_rate: u64,
+    pub lock_duration: i64,
+    pub tge_ts: i64,
+    pub lock_from_tge: bool,
+    pub delivery_mode: DeliveryMode,
+    pub withdrawal_delay: i64
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Default, Clone)]
+pub struct GlobalConfigParams {
+    pub fee_wallet: Pubkey,
+    pub default_fee_bps: u64,
+    pub creation_fee: u64,
+    pub payment_mints: Vec<Pubkey>
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Default, Clone)]
+pub struct MetadataParams {
+    pub name: String,
+    pub description: String,
+    pub website_uri: String,
+    pub whitepaper_uri: String,
+    pub image_uri: String,
+    pub symbol: String,
+    pub twitter_uri: String,
+    pub telegram_uri: String,
+    pub discord_uri: String
+}
+
+impl MetadataParams {
+    // Strings must fit in the space reserved by `PresaleMetadata::LEN`
+    pub fn validate(&self) -> ProgramResult {
+        if self.name.len() > MAX_NAME_LEN
+            || self.description.len() > MAX_DESCRIPTION_LEN
+            || self.website_uri.len() > MAX_URI_LEN
+            || self.whitepaper_uri.len() > MAX_URI_LEN
+            || self.image_uri.len() > MAX_URI_LEN
+            || self.symbol.len() > MAX_SYMBOL_LEN
+            || self.twitter_uri.len() > MAX_URI_LEN
+            || self.telegram_uri.len() > MAX_URI_LEN
+            || self.discord_uri.len() > MAX_URI_LEN {
+            return Err(ErrorCode::MetadataTooLong.into())
+        }
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Default, Clone)]
+pub struct RoundParams {
+    pub token_price: u64,
+    pub allocation: u64,
+    pub lock_rate: u64,
+    pub lock_duration: i64
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub enum DeliveryMode {
+    Immediate, // unlocked WEN is transferred at purchase
+    Deferred // unlocked WEN is claimed after the sale
+}
+
+// `#[default]` on enum variants needs a newer rustc than the BPF toolchain
+#[allow(clippy::derivable_impls)]
+impl Default for DeliveryMode {
+    fn default() -> Self {
+        DeliveryMode::Immediate
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub enum PresaleStatus {
+    Pending, // before start time
+    Active, // between start and end time
+    Ended, // after end time, waiting to be finalized or cancelled
+    Finalized, // sale succeeded
+    Cancelled // sale aborted, buyers can refund
+}
+
+#[allow(clippy::derivable_impls)]
+impl Default for PresaleStatus {
+    fn default() -> Self {
+        PresaleStatus::Pending
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub enum Role {
+    Treasurer,
+    Operator,
+    AllocationManager
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub enum PoolAsset {
+    Usdc,
+    Wen
+}
+
+#[allow(clippy::derivable_impls)]
+impl Default for PoolAsset {
+    fn default() -> Self {
+        PoolAsset::Usdc
+    }
+}
+
+// Stored inline in `Proposal`, so the large variant is fine
+#[allow(clippy::large_enum_variant)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub enum ProposalAction {
+    Withdraw { asset: PoolAsset, amount: u64, destination: Pubkey },
+    SetTokenPrice { token_price: u64 },
+    SetContributionLimits { min_contribution: u64, max_contribution: u64 },
+    SetAdmins { admins: [Pubkey; MAX_ADMINS], admin_count: u8, threshold: u8 },
+    SetTge { tge_ts: i64 },
+    GrantRole { role: Role, authority: Pubkey },
+    Allocate { user: Pubkey, amount: u64 } // `user` is the former holder's wallet
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Default, Clone)]
+pub struct PoolBumps {
+    pub presale_account: u8,
+    pub pool_usdc: u8,
+    pub pool_wen: u8,
+    pub registry_entry: u8
+}
+
+#[event]
+pub struct PresaleInitialized {
+    pub presale: Pubkey,
+    pub creator: Pubkey,
+    pub presale_id: u64,
+    pub registry_id: u64,
+    pub usdc_mint: Pubkey,
+    pub wen_mint: Pubkey,
+    pub start_ts: i64,
+    pub end_ts: i64,
+    pub timestamp: i64
+}
+
+#[event]
+pub struct Purchased {
+    pub presale: Pubkey,
+    pub buyer: Pubkey,
+    pub round: u8, // `NO_ACTIVE_ROUND` for presale terms
+    pub paid: u64, // USDC
+    pub received: u64, // unlocked WEN, pending when deferred
+    pub locked: u64, // locked WEN
+    pub deferred: bool,
+    pub timestamp: i64
+}
+
+#[event]
+pub struct Claimed {
+    pub presale: Pubkey,
+    pub user: Pubkey,
+    pub amount: u64,
+    pub locked: bool, // locked WEN, otherwise pending WEN of deferred delivery
+    pub timestamp: i64
+}
+
+#[event]
+pub struct Withdrawn {
+    pub presale: Pubkey,
+    pub authority: Pubkey, // treasurer or executing admin
+    pub asset: PoolAsset,
+    pub destination: Pubkey,
+    pub amount: u64, // taken from the pool, fee included
+    pub fee: u64,
+    pub timestamp: i64
+}
+
+#[event]
+pub struct FormerHolderAllocated {
+    pub presale: Pubkey,
+    pub user: Pubkey,
+    pub amount: u64,
+    pub lock_duration: i64,
+    pub timestamp: i64
+}
+
+#[event]
+pub struct Refunded {
+    pub presale: Pubkey,
+    pub user: Pubkey,
+    pub amount: u64, // USDC
+    pub returned: u64, // unlocked WEN sent back to the pool
+    pub timestamp: i64
+}
+
+#[event]
+pub struct WithdrawalScheduled {
+    pub presale: Pubkey,
+    pub withdrawal: Pubkey,
+    pub id: u64,
+    pub asset: PoolAsset,
+    pub amount: u64,
+    pub destination: Pubkey,
+    pub executable_ts: i64,
+    pub timestamp: i64
+}
+
+#[event]
+pub struct WithdrawalCancelled {
+    pub presale: Pubkey,
+    pub withdrawal: Pubkey,
+    pub id: u64,
+    pub timestamp: i64
+}
+
+#[event]
+pub struct PresaleFinalized {
+    pub presale: Pubkey,
+    pub total_raised: u64,
+    pub timestamp: i64
+}
+
+#[event]
+pub struct PresaleCancelled {
+    pub presale: Pubkey,
+    pub total_raised: u64, // USDC open for refunds
+    pub timestamp: i64
+}
+
+#[event]
+pub struct PauseChanged {
+    pub presale: Pubkey,
+    pub purchases_paused: bool,
+    pub claims_paused: bool,
+    pub timestamp: i64
+}
+
+#[event]
+pub struct OwnerProposed {
+    pub presale: Pubkey,
+    pub owner: Pubkey,
+    pub pending_owner: Pubkey, // default when the proposal is cancelled
+    pub timestamp: i64
+}
+
+#[event]
+pub struct OwnerChanged {
+    pub presale: Pubkey,
+    pub previous_owner: Pubkey,
+    pub owner: Pubkey,
+    pub timestamp: i64
+}
+
+#[event]
+pub struct RoleChanged {
+    pub presale: Pubkey,
+    pub role: Role,
+    pub authority: Pubkey, // default when revoked
+    pub timestamp: i64
+}
+
+#[event]
+pub struct AdminsChanged {
+    pub presale: Pubkey,
+    pub admin_count: u8,
+    pub threshold: u8,
+    pub admin_version: u32,
+    pub timestamp: i64
+}
+
+#[event]
+pub struct ProposalCreated {
+    pub presale: Pubkey,
+    pub proposal: Pubkey,
+    pub id: u64,
+    pub proposer: Pubkey,
+    pub action: ProposalAction,
+    pub timestamp: i64
+}
+
+#[event]
+pub struct ProposalApproved {
+    pub presale: Pubkey,
+    pub proposal: Pubkey,
+    pub id: u64,
+    pub admin: Pubkey,
+    pub approvals: u16,
+    pub approved: bool, // approvals reached the threshold
+    pub timestamp: i64
+}
+
+#[event]
+pub struct ConfigUpdated {
+    pub global_config: Pubkey,
+    pub admin: Pubkey,
+    pub fee_wallet: Pubkey,
+    pub default_fee_bps: u64,
+    pub creation_fee: u64, // lamports
+    pub payment_mint_count: u8,
+    pub timestamp: i64
+}
+
+#[event]
+pub struct ConfigAdminProposed {
+    pub global_config: Pubkey,
+    pub admin: Pubkey,
+    pub pending_admin: Pubkey, // default when the proposal is cancelled
+    pub timestamp: i64
+}
+
+#[event]
+pub struct ConfigAdminChanged {
+    pub global_config: Pubkey,
+    pub previous_admin: Pubkey,
+    pub admin: Pubkey,
+    pub timestamp: i64
 }
 
-#[derive(Accounts)]
-pub struct WithdrawUSDC<'info> {
-    // Payable account (User wallet)
-    #[account(mut)]
-    pub user_authority: Signer<'info>,
-    // TODO replace these with the ATA constraints when possible. 
-    // User's USDC token account
-    #[account(
-        constraint = user_usdc.owner ==user_authority.key(),
-        constraint = user_usdc.mint == usdc_mint.key()
-    )]
-    pub user_usdc: Account<'info, TokenAccount>,
-    
-    // Pool for USDC 
-    #[account(
-        seeds = [presale_account.presale_title.as_ref().trim_ascii_whitespace(), b"pool_usdc".as_ref()],
-        bump = presale_account.bumps.pool_usdc
-    )]
-    pub pool_usdc: Account<'info, TokenAccount>,
+#[event]
+pub struct MetadataUpdated {
+    pub presale: Pubkey,
+    pub metadata: Pubkey,
+    pub created: bool,
+    pub timestamp: i64
+}
 
-    // USD coin
-    #[account(mut)]
-    pub usdc_mint: Account<'info, Mint>,
-    
-    #[account(
-        seeds = [presale_account.presale_title.as_ref().trim_ascii_whitespace()],
-        bump = presale_account.bumps.presale_account,
-        has_one = usdc_mint
-    )]
-    pub presale_account: Box<Account<'info, PresaleAccount>>,
-    
-    // Programs and Sysvars
-    pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
-    pub rent: Sysvar<'info, Rent>,
+#[event]
+pub struct TokenPriceChanged {
+    pub presale: Pubkey,
+    pub token_price: u64,
+    pub timestamp: i64
 }
 
+#[event]
+pub struct ContributionLimitsChanged {
+    pub presale: Pubkey,
+    pub min_contribution: u64,
+    pub max_contribution: u64,
+    pub timestamp: i64
+}
 
-// NOTE: we need to validate the owner
-#[derive(Accounts)]
-pub struct WithdrawWenToken<'info> {
-    // Payable account (User wallet)
-    #[account(mut)]
-    pub user_authority: Signer<'info>,
+#[event]
+pub struct TgeChanged {
+    pub presale: Pubkey,
+    pub tge_ts: i64,
+    pub timestamp: i64
+}
 
-    // User's WEN token account
-    #[account(
-        constraint = user_wen.owner ==user_authority.key(),
-        constraint = user_wen.mint == wen_mint.key()
-    )]
-    pub user_wen: Account<'info, TokenAccount>,
-    
-    #[account(mut,
-        seeds = [presale_account.presale_title.as_ref().trim_ascii_whitespace(), b"pool_wen".as_ref()],
-        bump = presale_account.bumps.pool_wen)]
-    pub pool_wen: Account<'info, TokenAccount>,
+#[event]
+pub struct RoundCreated {
+    pub presale: Pubkey,
+    pub round: Pubkey,
+    pub index: u8,
+    pub token_price: u64,
+    pub allocation: u64, // WEN
+    pub lock_rate: u64,
+    pub lock_duration: i64,
+    pub timestamp: i64
+}
 
-    // WEN token
-    #[account(mut)]
-    pub wen_mint: Account<'info, Mint>,
-    
-    #[account(
-        seeds = [presale_account.presale_title.as_ref().trim_ascii_whitespace()],
-        bump = presale_account.bumps.presale_account,
-        has_one = wen_mint
-    )]
-    pub presale_account: Box<Account<'info, PresaleAccount>>,
-    
-    // Programs and Sysvars
-    pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
-    pub rent: Sysvar<'info, Rent>,
+#[event]
+pub struct ActiveRoundChanged {
+    pub presale: Pubkey,
+    pub round: Pubkey,
+    pub index: u8,
+    pub timestamp: i64
 }
 
-#[derive(Accounts)]
-pub struct FormerHoldersList<'info> {
-    // Payable account (Owner wallet)
-    #[account(mut)]
-    pub user_authority: Signer<'info>,
-    // User's info
-    #[account(mut)]
-    pub user_account: Account<'info, UserInfoAccount>,
-    
-    #[account(
-        seeds = [presale_account.presale_title.as_ref().trim_ascii_whitespace()],
-        bump = presale_account.bumps.presale_account
-    )]
-    pub presale_account: Box<Account<'info, PresaleAccount>>,
-    
-    // Programs and Sysvars
-    pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
-    pub rent: Sysvar<'info, Rent>,
+#[event]
+pub struct WithdrawDestinationChanged {
+    pub presale: Pubkey,
+    pub destination: Pubkey,
+    pub added: bool, // otherwise removed
+    pub timestamp: i64
 }
 
-#[account]
-#[derive(Default)]
-pub struct PresaleAccount {
-    pub presale_title: [u8; 10], // Setting an arbitrary max of ten characters in the presale name
-    pub bumps: PoolBumps,
-    pub presale_owner: Pubkey, // owner of program
-    pub usdc_mint: Pubkey,
-    pub wen_mint: Pubkey,
-    pub pool_usdc: Pubkey,
-    pub pool_wen: Pubkey
+#[event]
+pub struct UserAccountChanged {
+    pub presale: Pubkey,
+    pub user: Pubkey,
+    pub user_account: Pubkey,
+    pub closed: bool, // otherwise initialized
+    pub timestamp: i64
 }
 
-#[account]
-#[derive(Default)]
-pub struct UserInfoAccount {
-    pub owner: Pubkey,
-    pub bump: u8,
-    pub deposit_amount: u64,
-    pub locked_amount: u64,
-    pub last_deposit_ts: i64
+#[event]
+pub struct PresaleClosed {
+    pub presale: Pubkey,
+    pub owner: Pubkey, // received the pool rent
+    pub timestamp: i64
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Default, Clone)]
-pub struct PoolBumps {
-    pub presale_account: u8,
-    pub pool_usdc: u8,
-    pub pool_wen: u8
+#[event]
+pub struct ProposalExecuted {
+    pub presale: Pubkey,
+    pub proposal: Pubkey,
+    pub id: u64,
+    pub executor: Pubkey, // executing admin
+    pub timestamp: i64
 }
 
 #[error]
@@ -596,24 +3571,780 @@ pub enum ErrorCode {
     #[msg("You have no right to call this function")]
     NotRight,
     #[msg("Not enough USD coin")]
-    LowPoolUSDC
+    LowPoolUSDC,
+    #[msg("Token price must be greater than zero")]
+    InvalidPrice,
+    #[msg("Calculation overflow")]
+    MathOverflow,
+    #[msg("Sale start time must be before end time")]
+    InvalidSchedule,
+    #[msg("Sale has not started yet")]
+    SaleNotStarted,
+    #[msg("Sale has already started")]
+    SaleStarted,
+    #[msg("Sale has ended")]
+    SaleEnded,
+    #[msg("Sale has not ended yet")]
+    SaleNotEnded,
+    #[msg("Soft cap must not exceed a non-zero hard cap")]
+    InvalidCap,
+    #[msg("Purchase exceeds the hard cap")]
+    HardCapReached,
+    #[msg("Soft cap was not reached")]
+    SoftCapNotReached,
+    #[msg("Soft cap was reached, refund is not available")]
+    RefundNotAvailable,
+    #[msg("Nothing to refund")]
+    NothingToRefund,
+    #[msg("Min contribution must not exceed a non-zero max contribution")]
+    InvalidContributionLimits,
+    #[msg("Deposit is below the min contribution per wallet")]
+    BelowMinContribution,
+    #[msg("Deposit exceeds the max contribution per wallet")]
+    AboveMaxContribution,
+    #[msg("Too many rounds")]
+    TooManyRounds,
+    #[msg("Invalid round config")]
+    InvalidRound,
+    #[msg("Presale has an active round, use purchase_in_round")]
+    RoundRequired,
+    #[msg("Round is not active")]
+    RoundNotActive,
+    #[msg("Purchase exceeds the round allocation")]
+    RoundAllocationReached,
+    #[msg("Vesting period must not be negative")]
+    InvalidVesting,
+    #[msg("Too many locked purchases with different lock schedules in this wallet")]
+    TooManyTranches,
+    #[msg("Lock rate must be at most 10000 bps and lock duration at most 4 years")]
+    InvalidLockTerms,
+    #[msg("TGE must be in the future and not before the sale ends")]
+    InvalidTge,
+    #[msg("TGE has already passed")]
+    TgePassed,
+    #[msg("Invalid WEN token account")]
+    InvalidWenAccount,
+    #[msg("Not allowed in the current presale status")]
+    InvalidStatus,
+    #[msg("Sale has been cancelled")]
+    SaleCancelled,
+    #[msg("Paused by the presale owner")]
+    Paused,
+    #[msg("Invalid new owner")]
+    InvalidOwner,
+    #[msg("No owner proposal")]
+    NoPendingOwner,
+    #[msg("Invalid role authority, use revoke_role to remove a role")]
+    InvalidRoleAuthority,
+    #[msg("Multisig is on, use a proposal")]
+    MultisigRequired,
+    #[msg("Multisig is off")]
+    MultisigDisabled,
+    #[msg("Admins must be unique, at most 10 and not fewer than the threshold")]
+    InvalidAdmins,
+    #[msg("Proposal was executed or admins have changed")]
+    ProposalClosed,
+    #[msg("Not enough approvals")]
+    NotEnoughApprovals,
+    #[msg("Wrong instruction for this proposal")]
+    WrongProposalKind,
+    #[msg("Destination doesn't match")]
+    InvalidDestination,
+    #[msg("Pool doesn't match")]
+    InvalidPool,
+    #[msg("Withdrawal delay must be at most 30 days")]
+    InvalidWithdrawalDelay,
+    #[msg("Withdrawal delay is on, use schedule_withdrawal")]
+    TimelockRequired,
+    #[msg("Withdrawal delay has not passed yet")]
+    WithdrawalLocked,
+    #[msg("Destination is not a registered withdrawal destination")]
+    DestinationNotAllowed,
+    #[msg("Too many withdrawal destinations")]
+    TooManyDestinations,
+    #[msg("Platform fee is too high")]
+    InvalidFee,
+    #[msg("User still has locked, pending or refundable tokens")]
+    UserAccountNotEmpty,
+    #[msg("Pools still hold tokens or WEN is owed to buyers")]
+    PresaleNotEmpty,
+    #[msg("Presale name must be 1 to 32 bytes")]
+    InvalidName,
+    #[msg("Metadata field is too long")]
+    MetadataTooLong,
+    #[msg("Invalid launchpad config")]
+    InvalidConfig,
+    #[msg("Payment mint is not allowed by the launchpad")]
+    PaymentMintNotAllowed,
+    #[msg("Round accounts of the refunded purchases are missing")]
+    RoundAccountMissing,
+    #[msg("User account doesn't match the proposal")]
+    InvalidUserAccount
+}
+
+// Take USDC from the user and deliver WEN token with the given price and lock terms.
+// Returns the amount of WEN token bought.
+fn process_purchase<'info>(
+    accounts: &mut DepositUsdcForWenToken<'info>,
+    amount: u64,
+    token_price: u64,
+    lock_rate: u64,
+    lock_duration: i64
+) -> Result<u64> {
+    if accounts.presale_account.purchases_paused {
+        return Err(ErrorCode::Paused.into())
+    }
+
+    if amount < 1 {
+        return Err(ErrorCode::InvalidAmount.into())
+    }
+
+    if accounts.user_usdc.amount < amount {
+        return Err(ErrorCode::LowUSDC.into())
+    }
+
+    let clock = Clock::get()?; // Returns real-world time in second uint
+    match accounts.presale_account.current_status(clock.unix_timestamp) {
+        PresaleStatus::Active => {},
+        PresaleStatus::Pending => return Err(ErrorCode::SaleNotStarted.into()),
+        PresaleStatus::Cancelled => return Err(ErrorCode::SaleCancelled.into()),
+        _ => return Err(ErrorCode::SaleEnded.into())
+    }
+
+    // Total USDC accepted can't exceed the hard cap
+    let total_raised = accounts.presale_account.total_raised
+        .checked_add(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    if total_raised > accounts.presale_account.hard_cap {
+        return Err(ErrorCode::HardCapReached.into())
+    }
+
+    // Per-wallet limits apply to the total deposit of the user
+    let deposit_amount = accounts.user_account.deposit_amount
+        .checked_add(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    if deposit_amount < accounts.presale_account.min_contribution {
+        return Err(ErrorCode::BelowMinContribution.into())
+    }
+    if deposit_amount > accounts.presale_account.max_contribution {
+        return Err(ErrorCode::AboveMaxContribution.into())
+    }
+
+    // Transfer user's USDC to pool USDC account.
+    {
+        let cpi_accounts = Transfer {
+            // storer address (user address)
+            from: accounts.user_usdc.to_account_info(),
+            to: accounts.pool_usdc.to_account_info(),
+            authority: accounts.user_authority.to_account_info(),
+        };
+
+        let cpi_program = accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        token::transfer(cpi_ctx, amount)?;
+    }
+
+    // USDC decimal is 6 and WEN decimal is 9
+    let wen_amount = calculate_wen_amount(amount, token_price)?;
+    if wen_amount < 1 {
+        return Err(ErrorCode::InvalidAmount.into())
+    }
+    let lock_amount = (wen_amount as u128 * lock_rate as u128 / BPS_DENOMINATOR as u128) as u64;
+    let spend_amount = wen_amount - lock_amount;
+
+    // In deferred mode WEN token is only recorded and claimed later.
+    let deferred = accounts.presale_account.delivery_mode == DeliveryMode::Deferred;
+
+    // Pool must hold the WEN delivered now plus everything owed to buyers
+    let reserved_amount = if deferred { wen_amount } else { lock_amount };
+    let reserved_wen = accounts.presale_account.reserved_wen
+        .checked_add(reserved_amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let delivered_amount = if deferred { 0 } else { spend_amount };
+    if (accounts.pool_wen.amount as u128) < reserved_wen as u128 + delivered_amount as u128 {
+        return Err(ErrorCode::LowPoolWen.into())
+    }
+
+    // Transfer WEN token from pool token account to user's token account.
+    if !deferred {
+        check_user_wen(&accounts.user_wen, &accounts.user_authority.key(), &accounts.wen_mint.key())?;
+
+        transfer_from_pool(
+            &accounts.presale_account,
+            &accounts.pool_wen,
+            accounts.user_wen.to_account_info(),
+            &accounts.token_program,
+            spend_amount
+        )?;
+    }
+
+    let first_purchase = !accounts.user_account.participated;
+    let lock_from_tge = accounts.presale_account.lock_from_tge;
+
+    let user_account = &mut accounts.user_account;
+    user_account.participated = true;
+
+    // Lock some tokens and will be able to claim after `lock_duration` seconds.
+    if lock_amount > 0 {
+        user_account.add_tranche(lock_amount, clock.unix_timestamp, lock_duration, lock_from_tge)?;
+    }
+    if deferred {
+        user_account.pending_amount = user_account.pending_amount
+            .checked_add(spend_amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+    } else {
+        user_account.unlocked_amount = user_account.unlocked_amount
+            .checked_add(spend_amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+    }
+    user_account.deposit_amount = deposit_amount;
+    user_account.last_deposit_ts = clock.unix_timestamp;
+
+    let presale_account = &mut accounts.presale_account;
+    presale_account.total_raised = total_raised;
+    presale_account.reserved_wen = reserved_wen;
+    presale_account.record_purchase(first_purchase, spend_amount, lock_amount, clock.unix_timestamp)?;
+
+    emit!(Purchased {
+        presale: presale_account.key(),
+        buyer: accounts.user_authority.key(),
+        round: presale_account.active_round,
+        paid: amount,
+        received: spend_amount,
+        locked: lock_amount,
+        deferred,
+        timestamp: clock.unix_timestamp
+    });
+
+    Ok(wen_amount)
+}
+
+// Transfer tokens out of a presale pool, signed by the presale account
+fn transfer_from_pool<'info>(
+    presale_account: &Account<'info, PresaleAccount>,
+    pool: &Account<'info, TokenAccount>,
+    destination: AccountInfo<'info>,
+    token_program: &Program<'info, Token>,
+    amount: u64
+) -> ProgramResult {
+    let cpi_accounts = Transfer {
+        from: pool.to_account_info(),
+        to: destination,
+        authority: presale_account.to_account_info(),
+    };
+    let cpi_program = token_program.to_account_info();
+
+    presale_account.with_signer(|signer| {
+        token::transfer(CpiContext::new_with_signer(cpi_program, cpi_accounts, signer), amount)
+    })
+}
+
+// Close an empty presale pool, signed by the presale account
+fn close_pool<'info>(
+    presale_account: &Account<'info, PresaleAccount>,
+    pool: &Account<'info, TokenAccount>,
+    destination: AccountInfo<'info>,
+    token_program: &Program<'info, Token>
+) -> ProgramResult {
+    let cpi_accounts = CloseAccount {
+        account: pool.to_account_info(),
+        destination,
+        authority: presale_account.to_account_info(),
+    };
+    let cpi_program = token_program.to_account_info();
+
+    presale_account.with_signer(|signer| {
+        token::close_account(CpiContext::new_with_signer(cpi_program, cpi_accounts, signer))
+    })
+}
+
+// Withdraw `amount` from a presale pool and return the fee. The platform fee is taken
+// from USDC withdrawals and the destination gets the rest.
+fn withdraw_from_pool<'info>(
+    presale_account: &mut Account<'info, PresaleAccount>,
+    asset: PoolAsset,
+    pool: &Account<'info, TokenAccount>,
+    destination: AccountInfo<'info>,
+    fee_recipient: &Account<'info, TokenAccount>,
+    token_program: &Program<'info, Token>,
+    amount: u64
+) -> Result<u64> {
+    let fee = match asset {
+        PoolAsset::Usdc => presale_account.platform_fee(amount),
+        PoolAsset::Wen => 0
+    };
+
+    if fee > 0 {
+        transfer_from_pool(presale_account, pool, fee_recipient.to_account_info(), token_program, fee)?;
+    }
+    transfer_from_pool(presale_account, pool, destination, token_program, amount - fee)?;
+
+    presale_account.fees_collected = presale_account.fees_collected
+        .checked_add(fee)
+        .ok_or(ErrorCode::MathOverflow)?;
+    Ok(fee)
+}
+
+fn emit_config_updated(global_config: &Account<GlobalConfig>) -> ProgramResult {
+    emit!(ConfigUpdated {
+        global_config: global_config.key(),
+        admin: global_config.admin,
+        fee_wallet: global_config.fee_wallet,
+        default_fee_bps: global_config.default_fee_bps,
+        creation_fee: global_config.creation_fee,
+        payment_mint_count: global_config.payment_mint_count,
+        timestamp: Clock::get()?.unix_timestamp // Returns real-world time in second uint
+    });
+    Ok(())
+}
+
+// User's WEN token account is unchecked in accounts because deferred delivery doesn't need it,
+// so it's validated here whenever tokens are moved to or from it.
+fn check_user_wen(user_wen: &AccountInfo, user_authority: &Pubkey, wen_mint: &Pubkey) -> ProgramResult {
+    let user_wen = Account::<TokenAccount>::try_from(user_wen)?;
+    if user_wen.owner != *user_authority || user_wen.mint != *wen_mint {
+        return Err(ErrorCode::InvalidWenAccount.into())
+    }
+    Ok(())
 }
 
-/// Trait to allow trimming ascii whitespace from a &[u8].
-pub trait TrimAsciiWhitespace {
-    /// Trim ascii whitespace (based on `is_ascii_whitespace()`) from the
-    /// start and end of a slice.
-    fn trim_ascii_whitespace(&self) -> &[u8];
+// Lock rate can't exceed 100% and lock duration must be in [0, MAX_LOCK_DURATION]
+pub fn validate_lock_terms(lock_rate: u64, lock_duration: i64) -> ProgramResult {
+    if lock_rate > BPS_DENOMINATOR || !(0..=MAX_LOCK_DURATION).contains(&lock_duration) {
+        return Err(ErrorCode::InvalidLockTerms.into())
+    }
+    Ok(())
+}
+
+// Locked WEN token released at `now`.
+// The unlocked part (TGE unlock) is delivered at purchase, nothing more is released
+// before `unlock_ts` (cliff) and the locked part is released linearly over `vesting_period`.
+pub fn calculate_vested_amount(locked_amount: u64, unlock_ts: i64, vesting_period: i64, now: i64) -> u64 {
+    if now < unlock_ts {
+        return 0
+    }
+    let elapsed = now - unlock_ts;
+    if vesting_period < 1 || elapsed >= vesting_period {
+        return locked_amount
+    }
+    (locked_amount as u128 * elapsed as u128 / vesting_period as u128) as u64
+}
+
+// Convert USDC amount into WEN amount with the presale price
+pub fn calculate_wen_amount(amount: u64, token_price: u64) -> Result<u64> {
+    let wen_amount = (amount as u128)
+        .checked_mul(WEN_UNIT as u128)
+        .ok_or(ErrorCode::MathOverflow)?
+        / token_price as u128;
+    if wen_amount > u64::MAX as u128 {
+        return Err(ErrorCode::MathOverflow.into())
+    }
+    Ok(wen_amount as u64)
 }
 
-impl<T: Deref<Target = [u8]>> TrimAsciiWhitespace for T {
-       
-    fn trim_ascii_whitespace(&self) -> &[u8] {
-        let from = match self.iter().position(|x| !x.is_ascii_whitespace()) {
-            Some(i) => i,
-            None => return &self[0..0],
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn presale_with_times(start_ts: i64, end_ts: i64) -> PresaleAccount {
+        PresaleAccount {
+            start_ts,
+            end_ts,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn wen_amount_uses_price_per_wen_unit() {
+        assert_eq!(calculate_wen_amount(1_000_000, 1_000_000).unwrap(), WEN_UNIT);
+        assert_eq!(calculate_wen_amount(500_000, 1_000_000).unwrap(), WEN_UNIT / 2);
+        assert_eq!(calculate_wen_amount(0, 1_000_000).unwrap(), 0);
+    }
+
+    #[test]
+    fn wen_amount_overflow_fails() {
+        assert_eq!(
+            calculate_wen_amount(u64::MAX, 1).map_err(ProgramError::from),
+            Err(ErrorCode::MathOverflow.into())
+        );
+    }
+
+    #[test]
+    fn vested_amount_follows_cliff_and_vesting() {
+        assert_eq!(calculate_vested_amount(1_000, 100, 50, 99), 0);
+        assert_eq!(calculate_vested_amount(1_000, 100, 50, 100), 0);
+        assert_eq!(calculate_vested_amount(1_000, 100, 50, 125), 500);
+        assert_eq!(calculate_vested_amount(1_000, 100, 50, 150), 1_000);
+        assert_eq!(calculate_vested_amount(1_000, 100, 50, 1_000), 1_000);
+    }
+
+    #[test]
+    fn vested_amount_without_vesting_unlocks_at_cliff() {
+        assert_eq!(calculate_vested_amount(1_000, 100, 0, 99), 0);
+        assert_eq!(calculate_vested_amount(1_000, 100, 0, 100), 1_000);
+    }
+
+    #[test]
+    fn add_tranche_fills_free_slots() {
+        let mut user_account = UserInfoAccount::default();
+
+        user_account.add_tranche(100, 10, 20, false).unwrap();
+        user_account.add_tranche(50, 30, 20, false).unwrap();
+        assert_eq!(user_account.locked_amount, 150);
+        assert_eq!(user_account.tranches[0].amount, 100);
+        assert_eq!(user_account.tranches[1].amount, 50);
+        assert_eq!(user_account.tranches[1].start_ts, 30);
+    }
+
+    #[test]
+    fn add_tranche_merges_same_schedule() {
+        let mut user_account = UserInfoAccount::default();
+
+        user_account.add_tranche(100, 10, 20, false).unwrap();
+        user_account.add_tranche(50, 10, 20, false).unwrap();
+        user_account.add_tranche(30, 10, 40, false).unwrap();
+        assert_eq!(user_account.tranches[0].amount, 150);
+        assert_eq!(user_account.tranches[1].amount, 30);
+        assert_eq!(user_account.locked_amount, 180);
+    }
+
+    #[test]
+    fn add_tranche_merges_everything_locked_from_tge() {
+        let mut user_account = UserInfoAccount::default();
+        for i in 0..(MAX_TRANCHES as i64 * 2) {
+            user_account.add_tranche(100, i, 20, true).unwrap();
+        }
+        assert_eq!(user_account.tranches[0].amount, 100 * MAX_TRANCHES as u64 * 2);
+        assert_eq!(user_account.tranches[1].amount, 0);
+    }
+
+    #[test]
+    fn add_tranche_reuses_claimed_slots() {
+        let mut user_account = UserInfoAccount::default();
+        for i in 0..MAX_TRANCHES as i64 {
+            user_account.add_tranche(100, i, 20, false).unwrap();
+        }
+        assert_eq!(
+            user_account.add_tranche(100, MAX_TRANCHES as i64, 20, false),
+            Err(ErrorCode::TooManyTranches.into())
+        );
+
+        user_account.tranches[3].claimed_amount = 100;
+        user_account.add_tranche(70, 40, 20, false).unwrap();
+        assert_eq!(user_account.tranches[3].amount, 70);
+        assert_eq!(user_account.tranches[3].claimed_amount, 0);
+        assert_eq!(user_account.locked_amount, 100 * MAX_TRANCHES as u64 + 70);
+    }
+
+    #[test]
+    fn lock_terms_are_bounded() {
+        assert!(validate_lock_terms(0, 0).is_ok());
+        assert!(validate_lock_terms(BPS_DENOMINATOR, MAX_LOCK_DURATION).is_ok());
+        assert_eq!(validate_lock_terms(BPS_DENOMINATOR + 1, 0), Err(ErrorCode::InvalidLockTerms.into()));
+        assert_eq!(validate_lock_terms(0, -1), Err(ErrorCode::InvalidLockTerms.into()));
+        assert_eq!(validate_lock_terms(0, MAX_LOCK_DURATION + 1), Err(ErrorCode::InvalidLockTerms.into()));
+    }
+
+    #[test]
+    fn status_follows_sale_times() {
+        let presale_account = presale_with_times(100, 200);
+        assert!(presale_account.current_status(99) == PresaleStatus::Pending);
+        assert!(presale_account.current_status(100) == PresaleStatus::Active);
+        assert!(presale_account.current_status(199) == PresaleStatus::Active);
+        assert!(presale_account.current_status(200) == PresaleStatus::Ended);
+    }
+
+    #[test]
+    fn stored_status_overrides_sale_times() {
+        let mut presale_account = presale_with_times(100, 200);
+        presale_account.status = PresaleStatus::Cancelled;
+        assert!(presale_account.current_status(150) == PresaleStatus::Cancelled);
+        presale_account.status = PresaleStatus::Finalized;
+        assert!(presale_account.current_status(300) == PresaleStatus::Finalized);
+    }
+
+    #[test]
+    fn sale_terms_only_change_before_start() {
+        let presale_account = presale_with_times(100, 200);
+        assert!(presale_account.require_not_started(99).is_ok());
+        assert_eq!(presale_account.require_not_started(150), Err(ErrorCode::SaleStarted.into()));
+        assert_eq!(presale_account.require_not_started(250), Err(ErrorCode::SaleStarted.into()));
+    }
+
+    #[test]
+    fn apply_admins_sets_admins_and_version() {
+        let mut presale_account = PresaleAccount::default();
+        let admins = [Pubkey::new_unique(), Pubkey::new_unique()];
+
+        presale_account.apply_admins(&admins, 2).unwrap();
+        assert_eq!(presale_account.admin_count, 2);
+        assert_eq!(presale_account.approval_threshold, 2);
+        assert_eq!(presale_account.admin_version, 1);
+        assert_eq!(presale_account.admin_index(&admins[1]), Some(1));
+
+        presale_account.apply_admins(&[], 0).unwrap();
+        assert_eq!(presale_account.admin_count, 0);
+        assert_eq!(presale_account.admin_version, 2);
+        assert_eq!(presale_account.admin_index(&admins[0]), None);
+    }
+
+    #[test]
+    fn apply_admins_rejects_invalid_sets() {
+        let mut presale_account = PresaleAccount::default();
+        let admin = Pubkey::new_unique();
+        let invalid = Err(ErrorCode::InvalidAdmins.into());
+
+        assert_eq!(presale_account.apply_admins(&[admin], 2), invalid);
+        assert_eq!(presale_account.apply_admins(&[admin, admin], 1), invalid);
+        assert_eq!(presale_account.apply_admins(&[Pubkey::default()], 1), invalid);
+        assert_eq!(presale_account.apply_admins(&[admin; MAX_ADMINS + 1], 1), invalid);
+        assert_eq!(presale_account.admin_version, 0);
+    }
+
+    #[test]
+    fn apply_tge_only_moves_future_tge() {
+        let mut presale_account = presale_with_times(100, 200);
+
+        assert_eq!(presale_account.apply_tge(150, 50), Err(ErrorCode::InvalidTge.into()));
+        presale_account.apply_tge(300, 50).unwrap();
+        presale_account.apply_tge(400, 250).unwrap();
+        assert_eq!(presale_account.tge_ts, 400);
+        assert_eq!(presale_account.apply_tge(500, 400), Err(ErrorCode::TgePassed.into()));
+    }
+
+    #[test]
+    fn allocate_needs_pool_to_cover_reserved_wen() {
+        let mut presale_account = presale_with_times(100, 200);
+        presale_account.reserved_wen = 500;
+        let mut user_account = UserInfoAccount::default();
+
+        assert_eq!(
+            presale_account.allocate(&mut user_account, 600, 1_000, 50),
+            Err(ErrorCode::LowPoolWen.into())
+        );
+        presale_account.allocate(&mut user_account, 500, 1_000, 50).unwrap();
+        assert_eq!(presale_account.reserved_wen, 1_000);
+        assert_eq!(presale_account.total_allocated_wen, 500);
+        assert_eq!(user_account.allocated_amount, 500);
+        assert_eq!(user_account.locked_amount, 500);
+    }
+
+    #[test]
+    fn withdrawal_waits_for_delay() {
+        let presale_account = PresaleAccount {
+            withdrawal_delay: 100,
+            ..Default::default()
+        };
+
+        assert_eq!(presale_account.require_delay_passed(1_000, 1_099), Err(ErrorCode::WithdrawalLocked.into()));
+        assert!(presale_account.require_delay_passed(1_000, 1_100).is_ok());
+    }
+
+    #[test]
+    fn withdrawals_follow_presale_status() {
+        let mut presale_account = presale_with_times(100, 200);
+        presale_account.pool_usdc = Pubkey::new_unique();
+        presale_account.pool_wen = Pubkey::new_unique();
+        let pool_usdc = presale_account.pool_usdc;
+        let pool_wen = presale_account.pool_wen;
+        let invalid_status = Err(ErrorCode::InvalidStatus.into());
+
+        // Nothing can be announced while the sale runs
+        assert_eq!(presale_account.require_withdraw_status(PoolAsset::Usdc, 150), invalid_status);
+        assert_eq!(presale_account.require_withdraw_status(PoolAsset::Wen, 250), invalid_status);
+
+        presale_account.status = PresaleStatus::Cancelled;
+        assert_eq!(presale_account.require_withdrawable(PoolAsset::Usdc, &pool_usdc, 250), invalid_status);
+        assert!(presale_account.require_withdrawable(PoolAsset::Wen, &pool_wen, 250).is_ok());
+
+        presale_account.status = PresaleStatus::Finalized;
+        assert!(presale_account.require_withdrawable(PoolAsset::Usdc, &pool_usdc, 250).is_ok());
+        assert_eq!(
+            presale_account.require_withdrawable(PoolAsset::Usdc, &pool_wen, 250),
+            Err(ErrorCode::InvalidPool.into())
+        );
+    }
+
+    #[test]
+    fn platform_fee_is_share_of_withdrawal() {
+        let presale_account = PresaleAccount {
+            fee_bps: 250,
+            ..Default::default()
+        };
+        assert_eq!(presale_account.platform_fee(10_000_000), 250_000);
+        assert_eq!(presale_account.platform_fee(39), 0);
+        assert_eq!(presale_account.platform_fee(u64::MAX), 461_168_601_842_738_790);
+        assert_eq!(PresaleAccount::default().platform_fee(10_000_000), 0);
+    }
+
+    #[test]
+    fn user_account_closes_once_nothing_is_owed() {
+        let mut user_account = UserInfoAccount::default();
+        assert!(user_account.require_closable(Some(PresaleStatus::Active)).is_ok());
+
+        user_account.add_tranche(100, 10, 20, false).unwrap();
+        user_account.participated = true;
+        user_account.deposit_amount = 100;
+        let not_empty = Err(ErrorCode::UserAccountNotEmpty.into());
+        assert_eq!(user_account.require_closable(Some(PresaleStatus::Finalized)), not_empty);
+
+        user_account.claimed_amount = 100;
+        assert_eq!(user_account.require_closable(Some(PresaleStatus::Ended)), Err(ErrorCode::InvalidStatus.into()));
+        assert!(user_account.require_closable(Some(PresaleStatus::Finalized)).is_ok());
+        // Closed presale
+        assert!(user_account.require_closable(None).is_ok());
+
+        user_account.pending_amount = 1;
+        assert_eq!(user_account.require_closable(None), not_empty);
+    }
+
+    #[test]
+    fn presale_closes_once_finalized_and_empty() {
+        let mut presale_account = presale_with_times(100, 200);
+        assert_eq!(presale_account.require_closable(0, 0, 250), Err(ErrorCode::InvalidStatus.into()));
+
+        presale_account.status = PresaleStatus::Finalized;
+        let not_empty = Err(ErrorCode::PresaleNotEmpty.into());
+        assert_eq!(presale_account.require_closable(1, 0, 250), not_empty);
+        assert_eq!(presale_account.require_closable(0, 1, 250), not_empty);
+        presale_account.reserved_wen = 1;
+        assert_eq!(presale_account.require_closable(0, 0, 250), not_empty);
+
+        presale_account.reserved_wen = 0;
+        assert!(presale_account.require_closable(0, 0, 250).is_ok());
+    }
+
+    fn max_metadata_params() -> MetadataParams {
+        let uri = "u".repeat(MAX_URI_LEN);
+        MetadataParams {
+            name: "n".repeat(MAX_NAME_LEN),
+            description: "d".repeat(MAX_DESCRIPTION_LEN),
+            website_uri: uri.clone(),
+            whitepaper_uri: uri.clone(),
+            image_uri: uri.clone(),
+            symbol: "s".repeat(MAX_SYMBOL_LEN),
+            twitter_uri: uri.clone(),
+            telegram_uri: uri.clone(),
+            discord_uri: uri
+        }
+    }
+
+    #[test]
+    fn metadata_at_max_lengths_fits_account() {
+        let params = max_metadata_params();
+        params.validate().unwrap();
+
+        let mut metadata = PresaleMetadata::default();
+        metadata.apply(params);
+        assert_eq!(8 + metadata.try_to_vec().unwrap().len(), PresaleMetadata::LEN);
+    }
+
+    #[test]
+    fn metadata_over_max_lengths_fails() {
+        let too_long = Err(ErrorCode::MetadataTooLong.into());
+        let mut params = max_metadata_params();
+        params.name.push('n');
+        assert_eq!(params.validate(), too_long);
+
+        let mut params = max_metadata_params();
+        params.description.push('d');
+        assert_eq!(params.validate(), too_long);
+
+        let mut params = max_metadata_params();
+        params.symbol.push('s');
+        assert_eq!(params.validate(), too_long);
+
+        let mut params = max_metadata_params();
+        params.discord_uri.push('u');
+        assert_eq!(params.validate(), too_long);
+    }
+
+    #[test]
+    fn payment_mints_are_allowlisted() {
+        let usdc_mint = Pubkey::new_unique();
+        let other_mint = Pubkey::new_unique();
+        let mut global_config = GlobalConfig::default();
+
+        global_config.apply(GlobalConfigParams {
+            fee_wallet: Pubkey::new_unique(),
+            default_fee_bps: 250,
+            creation_fee: 0,
+            payment_mints: vec![usdc_mint]
+        }).unwrap();
+        assert!(global_config.is_payment_mint(&usdc_mint));
+        assert!(!global_config.is_payment_mint(&other_mint));
+        // Unused slots don't allow the default key
+        assert!(!global_config.is_payment_mint(&Pubkey::default()));
+
+        global_config.apply(GlobalConfigParams {
+            fee_wallet: global_config.fee_wallet,
+            default_fee_bps: 250,
+            creation_fee: 0,
+            payment_mints: vec![other_mint]
+        }).unwrap();
+        assert!(!global_config.is_payment_mint(&usdc_mint));
+        assert!(global_config.is_payment_mint(&other_mint));
+    }
+
+    #[test]
+    fn global_config_rejects_invalid_params() {
+        let mut global_config = GlobalConfig::default();
+        let params = GlobalConfigParams {
+            fee_wallet: Pubkey::new_unique(),
+            default_fee_bps: 250,
+            creation_fee: 0,
+            payment_mints: vec![]
+        };
+
+        let too_many_mints = GlobalConfigParams {
+            payment_mints: vec![Pubkey::new_unique(); MAX_PAYMENT_MINTS + 1],
+            ..params.clone()
+        };
+        assert_eq!(global_config.apply(too_many_mints), Err(ErrorCode::InvalidConfig.into()));
+
+        let no_fee_wallet = GlobalConfigParams {
+            fee_wallet: Pubkey::default(),
+            ..params.clone()
+        };
+        assert_eq!(global_config.apply(no_fee_wallet), Err(ErrorCode::InvalidConfig.into()));
+
+        let fee_too_high = GlobalConfigParams {
+            default_fee_bps: MAX_FEE_BPS + 1,
+            ..params
         };
-        let to = self.iter().rposition(|x| !x.is_ascii_whitespace()).unwrap();
-        &self[from..=to]
+        assert_eq!(global_config.apply(fee_too_high), Err(ErrorCode::InvalidFee.into()));
+    }
+
+    #[test]
+    fn refund_rolls_back_purchase_statistics() {
+        let mut presale_account = presale_with_times(100, 200);
+        let mut user_account = UserInfoAccount::default();
+
+        // Former holder allocation stays counted apart from the purchase
+        presale_account.allocate(&mut user_account, 300, 1_000, 150).unwrap();
+        presale_account.record_purchase(true, 600, 400, 150).unwrap();
+        user_account.participated = true;
+        user_account.deposit_amount = 1_000;
+        user_account.unlocked_amount = 600;
+        user_account.add_tranche(400, 150, 0, false).unwrap();
+        presale_account.total_raised = 1_000;
+
+        presale_account.record_refund(&user_account);
+        assert_eq!(presale_account.total_raised, 0);
+        assert_eq!(presale_account.total_unlocked_wen, 0);
+        assert_eq!(presale_account.total_locked_wen, 0);
+        assert_eq!(presale_account.total_allocated_wen, 0);
+        assert_eq!(presale_account.participant_count, 0);
+        assert_eq!(presale_account.first_purchase_ts, 150);
+    }
+
+    #[test]
+    fn refund_without_purchase_keeps_participants() {
+        let mut presale_account = presale_with_times(100, 200);
+        presale_account.record_purchase(true, 600, 0, 150).unwrap();
+
+        presale_account.record_refund(&UserInfoAccount::default());
+        assert_eq!(presale_account.participant_count, 1);
+        assert_eq!(presale_account.total_unlocked_wen, 600);
     }
 }
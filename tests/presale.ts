@@ -1,17 +1,377 @@
 import * as anchor from '@project-serum/anchor';
 import { Program } from '@project-serum/anchor';
+import { assert } from 'chai';
 import { Presale } from '../target/types/presale';
 
+const { Keypair, LAMPORTS_PER_SOL, PublicKey, SystemProgram, SYSVAR_RENT_PUBKEY, Transaction, TransactionInstruction } = anchor.web3;
+type PublicKey = anchor.web3.PublicKey;
+
+const BPF_LOADER_UPGRADEABLE_ID = new PublicKey('BPFLoaderUpgradeab1e11111111111111111111111');
+const TOKEN_PROGRAM_ID = new PublicKey('TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA');
+const MINT_SIZE = 82;
+const TOKEN_ACCOUNT_SIZE = 165;
+
+const USDC_UNIT = 1_000_000;
+const WEN_UNIT = 1_000_000_000;
+const FEE_BPS = 250;
+
 describe('presale', () => {
 
   // Configure the client to use the local cluster.
-  anchor.setProvider(anchor.Provider.env());
+  const provider = anchor.Provider.env();
+  anchor.setProvider(provider);
 
   const program = anchor.workspace.Presale as Program<Presale>;
+  const owner = provider.wallet.publicKey;
+
+  const buyer = Keypair.generate();
+  const feeWallet = Keypair.generate();
+
+  let usdcMint: PublicKey;
+  let wenMint: PublicKey;
+  let buyerUsdc: PublicKey;
+  let buyerWen: PublicKey;
+  let ownerUsdc: PublicKey;
+  let feeUsdc: PublicKey;
+  let globalConfig: PublicKey;
+
+  const now = async (): Promise<number> => {
+    const slot = await provider.connection.getSlot();
+    return await provider.connection.getBlockTime(slot);
+  };
+
+  const sleepUntil = async (ts: number) => {
+    while (await now() < ts) {
+      await new Promise((resolve) => setTimeout(resolve, 500));
+    }
+  };
+
+  const balance = async (account: PublicKey): Promise<number> => {
+    return Number((await provider.connection.getTokenAccountBalance(account)).value.amount);
+  };
+
+  // Minimal SPL token instructions, so the tests only need web3.js
+  const createTokenProgramAccount = async (space: number, data: Buffer, keys: PublicKey[]) => {
+    const account = Keypair.generate();
+    const tx = new Transaction().add(
+      SystemProgram.createAccount({
+        fromPubkey: owner,
+        newAccountPubkey: account.publicKey,
+        space,
+        lamports: await provider.connection.getMinimumBalanceForRentExemption(space),
+        programId: TOKEN_PROGRAM_ID
+      }),
+      new TransactionInstruction({
+        keys: [account.publicKey, ...keys, SYSVAR_RENT_PUBKEY].map((pubkey, i) => (
+          { pubkey, isSigner: false, isWritable: i === 0 }
+        )),
+        programId: TOKEN_PROGRAM_ID,
+        data
+      })
+    );
+    await provider.send(tx, [account]);
+    return account.publicKey;
+  };
+
+  // InitializeMint with `owner` as mint authority and no freeze authority
+  const createMint = async (decimals: number) => {
+    const data = Buffer.concat([Buffer.from([0, decimals]), owner.toBuffer(), Buffer.from([0])]);
+    return await createTokenProgramAccount(MINT_SIZE, data, []);
+  };
+
+  // InitializeAccount
+  const createTokenAccount = async (mint: PublicKey, holder: PublicKey) => {
+    return await createTokenProgramAccount(TOKEN_ACCOUNT_SIZE, Buffer.from([1]), [mint, holder]);
+  };
+
+  // MintTo signed by `owner`
+  const mintTo = async (mint: PublicKey, destination: PublicKey, amount: number) => {
+    const tx = new Transaction().add(
+      new TransactionInstruction({
+        keys: [
+          { pubkey: mint, isSigner: false, isWritable: true },
+          { pubkey: destination, isSigner: false, isWritable: true },
+          { pubkey: owner, isSigner: true, isWritable: false }
+        ],
+        programId: TOKEN_PROGRAM_ID,
+        data: Buffer.concat([Buffer.from([7]), new anchor.BN(amount).toArrayLike(Buffer, 'le', 8)])
+      })
+    );
+    await provider.send(tx);
+  };
+
+  // Creates a presale of `owner` selling WEN at 1 USDC and funds its WEN pool
+  const createPresale = async (presaleId: number, startTs: number, endTs: number) => {
+    const idBytes = new anchor.BN(presaleId).toArrayLike(Buffer, 'le', 8);
+    const [presaleAccount, presaleBump] = await PublicKey.findProgramAddress(
+      [Buffer.from('presale'), owner.toBuffer(), idBytes],
+      program.programId
+    );
+    const [poolUsdc, poolUsdcBump] = await PublicKey.findProgramAddress(
+      [presaleAccount.toBuffer(), Buffer.from('pool_usdc')],
+      program.programId
+    );
+    const [poolWen, poolWenBump] = await PublicKey.findProgramAddress(
+      [presaleAccount.toBuffer(), Buffer.from('pool_wen')],
+      program.programId
+    );
+    const config = await program.account.globalConfig.fetch(globalConfig);
+    const [registryEntry, registryBump] = await PublicKey.findProgramAddress(
+      [Buffer.from('registry'), config.presaleCount.toArrayLike(Buffer, 'le', 8)],
+      program.programId
+    );
+
+    await program.rpc.initialize(
+      new anchor.BN(presaleId),
+      'WEN presale ' + presaleId,
+      {
+        presaleAccount: presaleBump,
+        poolUsdc: poolUsdcBump,
+        poolWen: poolWenBump,
+        registryEntry: registryBump
+      },
+      {
+        tokenPrice: new anchor.BN(USDC_UNIT),
+        startTs: new anchor.BN(startTs),
+        endTs: new anchor.BN(endTs),
+        softCap: new anchor.BN(5 * USDC_UNIT),
+        hardCap: new anchor.BN(100 * USDC_UNIT),
+        minContribution: new anchor.BN(USDC_UNIT),
+        maxContribution: new anchor.BN(50 * USDC_UNIT),
+        vestingPeriod: new anchor.BN(0),
+        lockRate: new anchor.BN(5_000),
+        lockDuration: new anchor.BN(0),
+        tgeTs: new anchor.BN(0),
+        lockFromTge: false,
+        deliveryMode: { immediate: {} },
+        withdrawalDelay: new anchor.BN(0)
+      },
+      {
+        accounts: {
+          presaleAccount,
+          presaleOwner: owner,
+          globalConfig,
+          registryEntry,
+          feeWallet: feeWallet.publicKey,
+          usdcMint: usdcMint,
+          wenMint: wenMint,
+          poolUsdc,
+          poolWen,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          rent: SYSVAR_RENT_PUBKEY
+        }
+      }
+    );
+
+    await mintTo(wenMint, poolWen, 1_000 * WEN_UNIT);
+
+    const [userAccount, userBump] = await PublicKey.findProgramAddress(
+      [Buffer.from('user'), presaleAccount.toBuffer(), buyer.publicKey.toBuffer()],
+      program.programId
+    );
+    await program.rpc.initUserAccount(userBump, {
+      accounts: {
+        userAccount,
+        presaleAccount,
+        userAuthority: buyer.publicKey,
+        systemProgram: SystemProgram.programId,
+        rent: SYSVAR_RENT_PUBKEY
+      },
+      signers: [buyer]
+    });
+
+    return { presaleAccount, poolUsdc, poolWen, userAccount };
+  };
+
+  const purchase = async (presale, amount: number) => {
+    await program.rpc.purchase(new anchor.BN(amount), {
+      accounts: {
+        userAuthority: buyer.publicKey,
+        userAccount: presale.userAccount,
+        userUsdc: buyerUsdc,
+        usdcMint: usdcMint,
+        userWen: buyerWen,
+        wenMint: wenMint,
+        presaleAccount: presale.presaleAccount,
+        poolUsdc: presale.poolUsdc,
+        poolWen: presale.poolWen,
+        systemProgram: SystemProgram.programId,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        rent: SYSVAR_RENT_PUBKEY
+      },
+      signers: [buyer]
+    });
+  };
+
+  const claimLockedWen = async (presale) => {
+    await program.rpc.claimLockedWen({
+      accounts: {
+        userAuthority: buyer.publicKey,
+        userAccount: presale.userAccount,
+        userWen: buyerWen,
+        wenMint: wenMint,
+        poolWen: presale.poolWen,
+        presaleAccount: presale.presaleAccount,
+        systemProgram: SystemProgram.programId,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        rent: SYSVAR_RENT_PUBKEY
+      },
+      signers: [buyer]
+    });
+  };
+
+  before(async () => {
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(buyer.publicKey, 2 * LAMPORTS_PER_SOL)
+    );
+
+    usdcMint = await createMint(6);
+    wenMint = await createMint(9);
+
+    buyerUsdc = await createTokenAccount(usdcMint, buyer.publicKey);
+    buyerWen = await createTokenAccount(wenMint, buyer.publicKey);
+    ownerUsdc = await createTokenAccount(usdcMint, owner);
+    feeUsdc = await createTokenAccount(usdcMint, feeWallet.publicKey);
+
+    await mintTo(usdcMint, buyerUsdc, 100 * USDC_UNIT);
+  });
+
+  it('Sets up the launchpad config with the upgrade authority', async () => {
+    const [programData] = await PublicKey.findProgramAddress(
+      [program.programId.toBuffer()],
+      BPF_LOADER_UPGRADEABLE_ID
+    );
+    const [config, configBump] = await PublicKey.findProgramAddress(
+      [Buffer.from('config')],
+      program.programId
+    );
+    globalConfig = config;
+
+    await program.rpc.initGlobalConfig(
+      configBump,
+      {
+        feeWallet: feeWallet.publicKey,
+        defaultFeeBps: new anchor.BN(FEE_BPS),
+        creationFee: new anchor.BN(0),
+        paymentMints: [usdcMint]
+      },
+      {
+        accounts: {
+          admin: owner,
+          globalConfig,
+          programData,
+          systemProgram: SystemProgram.programId,
+          rent: SYSVAR_RENT_PUBKEY
+        }
+      }
+    );
+
+    const configAccount = await program.account.globalConfig.fetch(globalConfig);
+    assert.ok(configAccount.admin.equals(owner));
+    assert.ok(configAccount.feeWallet.equals(feeWallet.publicKey));
+    assert.equal(configAccount.presaleCount.toNumber(), 0);
+  });
+
+  it('Sells, unlocks and withdraws a finalized presale', async () => {
+    const startTs = await now() + 3;
+    const endTs = startTs + 6;
+    const presale = await createPresale(1, startTs, endTs);
+
+    await sleepUntil(startTs);
+    await purchase(presale, 10 * USDC_UNIT);
+
+    // Half of the WEN is delivered at purchase, the other half is locked
+    assert.equal(await balance(buyerWen), 5 * WEN_UNIT);
+    const userInfo = await program.account.userInfoAccount.fetch(presale.userAccount);
+    assert.equal(userInfo.depositAmount.toNumber(), 10 * USDC_UNIT);
+    assert.equal(userInfo.lockedAmount.toNumber(), 5 * WEN_UNIT);
+
+    // Nothing unlocks before the sale is finalized
+    try {
+      await claimLockedWen(presale);
+      assert.fail('claimed locked WEN before finalization');
+    } catch (err) {
+      assert.equal(err.msg, 'Not allowed in the current presale status');
+    }
+
+    await sleepUntil(endTs);
+    await program.rpc.finalizePresale({
+      accounts: {
+        userAuthority: owner,
+        presaleAccount: presale.presaleAccount
+      }
+    });
+
+    await claimLockedWen(presale);
+    assert.equal(await balance(buyerWen), 10 * WEN_UNIT);
+
+    await program.rpc.withdrawUsdc(new anchor.BN(10 * USDC_UNIT), {
+      accounts: {
+        userAuthority: owner,
+        destination: ownerUsdc,
+        poolUsdc: presale.poolUsdc,
+        globalConfig,
+        feeRecipient: feeUsdc,
+        usdcMint: usdcMint,
+        presaleAccount: presale.presaleAccount,
+        systemProgram: SystemProgram.programId,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        rent: SYSVAR_RENT_PUBKEY
+      }
+    });
+
+    // Platform fee goes to the launchpad fee wallet
+    const fee = 10 * USDC_UNIT * FEE_BPS / 10_000;
+    assert.equal(await balance(feeUsdc), fee);
+    assert.equal(await balance(ownerUsdc), 10 * USDC_UNIT - fee);
+    assert.equal(await balance(presale.poolUsdc), 0);
+  });
+
+  it('Refunds buyers of a cancelled presale', async () => {
+    const startTs = await now() + 3;
+    const presale = await createPresale(2, startTs, startTs + 600);
+
+    await sleepUntil(startTs);
+    const usdcBefore = await balance(buyerUsdc);
+    const wenBefore = await balance(buyerWen);
+    await purchase(presale, 10 * USDC_UNIT);
+
+    await program.rpc.cancelPresale({
+      accounts: {
+        userAuthority: owner,
+        presaleAccount: presale.presaleAccount
+      }
+    });
+
+    await program.rpc.refund({
+      accounts: {
+        userAuthority: buyer.publicKey,
+        userAccount: presale.userAccount,
+        userUsdc: buyerUsdc,
+        usdcMint: usdcMint,
+        userWen: buyerWen,
+        wenMint: wenMint,
+        presaleAccount: presale.presaleAccount,
+        poolUsdc: presale.poolUsdc,
+        poolWen: presale.poolWen,
+        systemProgram: SystemProgram.programId,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        rent: SYSVAR_RENT_PUBKEY
+      },
+      signers: [buyer]
+    });
+
+    // USDC comes back and the delivered WEN goes back to the pool
+    assert.equal(await balance(buyerUsdc), usdcBefore);
+    assert.equal(await balance(buyerWen), wenBefore);
+
+    const userInfo = await program.account.userInfoAccount.fetch(presale.userAccount);
+    assert.equal(userInfo.depositAmount.toNumber(), 0);
+    assert.equal(userInfo.lockedAmount.toNumber(), 0);
 
-  it('Is initialized!', async () => {
-    // Add your test here.
-    const tx = await program.rpc.initialize({});
-    console.log("Your transaction signature", tx);
+    const presaleInfo = await program.account.presaleAccount.fetch(presale.presaleAccount);
+    assert.equal(presaleInfo.totalRaised.toNumber(), 0);
+    assert.equal(presaleInfo.participantCount.toNumber(), 0);
   });
 });